@@ -0,0 +1,38 @@
+//! Frame time of the CPU renderer, `World::draw`, at a few resolutions and light positions.
+//!
+//! Criterion reports the time per frame, and the throughput line gives pixels per second; the
+//! time per pixel is its inverse.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use raytracing_rs::scene::Scene;
+use raytracing_rs::World;
+
+const RESOLUTIONS: [(u32, u32); 3] = [(640, 360), (1280, 720), (1920, 1080)];
+
+/// Light positions as fractions of the frame size: the default spot left of the circle, above
+/// it, and behind it on the right, which puts the most of the frame in shadow.
+const LIGHTS: [(&str, f32, f32); 3] = [("left", 0.16, 0.5), ("top", 0.5, 0.1), ("right", 0.95, 0.5)];
+
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    // A 1080p frame takes long enough on few cores that the default 100 samples drag on
+    group.sample_size(20);
+
+    for (width, height) in RESOLUTIONS {
+        group.throughput(Throughput::Elements(width as u64 * height as u64));
+        let mut frame = vec![0; width as usize * height as usize * 4];
+        for (name, fx, fy) in LIGHTS {
+            let mut scene = Scene { width, height, ..Scene::default() };
+            scene.lights[0].x = fx * width as f32;
+            scene.lights[0].y = fy * height as f32;
+            let world = World::new(scene);
+            group.bench_with_input(BenchmarkId::new(format!("{width}x{height}"), name), &world, |b, world| {
+                b.iter(|| world.draw(&mut frame));
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, draw);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! Time per call of the circle shadow test, `is_shadowed`, on its own, for each of the ways a
+//! ray can come out of it.
+//!
+//! Every case is one light, pixel and circle, so criterion's time per iteration is the time per
+//! call. The `x8` group runs the same cases through `is_shadowed_x8` with eight pixels spread
+//! along the ray, and its throughput line gives the calls per second of each lane.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use raytracing_rs::is_shadowed;
+use raytracing_rs::occluder::{is_shadowed_x8, LANES};
+use wide::f32x8;
+
+type Point = (f32, f32);
+
+/// The circle every case is tested against.
+const CIRCLE: (f32, f32, f32) = (100.0, 0.0, 20.0);
+
+/// Light and pixel for each case: a ray passing wide of the circle, pixels behind the light and
+/// short of the circle (both ruled out before the square root), pixels just past the circle and
+/// far beyond it, and a light inside it.
+const CASES: [(&str, Point, Point); 6] = [
+    ("miss", (0.0, 0.0), (200.0, 100.0)),
+    ("behind", (0.0, 0.0), (-100.0, 0.0)),
+    ("short", (0.0, 0.0), (50.0, 0.0)),
+    ("hit_near", (0.0, 0.0), (130.0, 0.0)),
+    ("hit_far", (0.0, 0.0), (1000.0, 5.0)),
+    ("light_inside", (100.0, 5.0), (300.0, 0.0)),
+];
+
+fn shadow(c: &mut Criterion) {
+    let (cx, cy, r) = CIRCLE;
+
+    let mut group = c.benchmark_group("is_shadowed");
+    for (name, (lx, ly), (px, py)) in CASES {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                is_shadowed(
+                    black_box(lx), black_box(ly), black_box(px), black_box(py),
+                    black_box(cx), black_box(cy), black_box(r),
+                )
+            });
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("is_shadowed_x8");
+    group.throughput(Throughput::Elements(LANES as u64));
+    for (name, (lx, ly), (px, py)) in CASES {
+        // Lanes spread over the last eighth of the way to the pixel, close enough that they all
+        // land in the same case
+        let lane = |i: usize, l: f32, p: f32| l + (p - l) * (1.0 - (LANES - 1 - i) as f32 / (8 * LANES) as f32);
+        let pxs = f32x8::new(std::array::from_fn(|i| lane(i, lx, px)));
+        let pys = f32x8::new(std::array::from_fn(|i| lane(i, ly, py)));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &(pxs, pys), |b, &(pxs, pys)| {
+            b.iter(|| {
+                is_shadowed_x8(
+                    black_box(lx), black_box(ly), black_box(pxs), black_box(pys),
+                    black_box(cx), black_box(cy), black_box(r),
+                )
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, shadow);
+criterion_main!(benches);
@@ -0,0 +1,126 @@
+//! Glow around the brightest parts of a finished frame, added over it as a post-process.
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::color::Rgb;
+
+/// Luminance above which pixels glow by default, on a 0 to 1 scale.
+pub const THRESHOLD: f32 = 0.8;
+/// Standard deviation of the glow's Gaussian by default, in frame pixels.
+pub const RADIUS: f32 = 8.0;
+
+/// Blurs whatever is brighter than `threshold` by `radius` and adds it back on top.
+#[derive(Clone, Copy, Serialize)]
+pub struct Bloom {
+    pub threshold: f32,
+    pub radius: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self { threshold: THRESHOLD, radius: RADIUS }
+    }
+}
+
+impl Bloom {
+    /// Add the glow to an RGBA `frame` of the given `width`.
+    ///
+    /// Each pixel keeps the part of its color above the threshold, which a separable Gaussian
+    /// then spreads out, one pass along the rows and one down the columns, each a line at a time
+    /// in parallel. The result is added to the frame, clipping at white.
+    pub fn apply(&self, frame: &mut [u8], width: u32) {
+        let width = width as usize;
+        if width == 0 || self.radius <= 0.0 {
+            return;
+        }
+        let height = frame.len() / 4 / width;
+        let bright: Vec<Rgb> = frame.par_chunks_exact(4).map(|p| self.bright_part(p)).collect();
+
+        // Across, then down. Both passes write whole lines for rayon to split up, so the second
+        // writes its columns as rows, transposed
+        let kernel = gaussian(self.radius);
+        let mut across = vec![[0.0; 3]; width * height];
+        across.par_chunks_exact_mut(width).enumerate().for_each(|(y, row)| {
+            blur_line(&kernel, |x| bright[y * width + x], row);
+        });
+        let mut down = vec![[0.0; 3]; width * height];
+        down.par_chunks_exact_mut(height).enumerate().for_each(|(x, column)| {
+            blur_line(&kernel, |y| across[y * width + x], column);
+        });
+
+        frame.par_chunks_exact_mut(4 * width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let glow = down[x * height + y];
+                for (c, g) in pixel[..3].iter_mut().zip(glow) {
+                    *c = (*c as f32 + g * 255.0).round().min(255.0) as u8;
+                }
+            }
+        });
+    }
+
+    /// What's left of an RGBA pixel's color once its luminance is brought down by the threshold,
+    /// keeping its hue.
+    fn bright_part(&self, pixel: &[u8]) -> Rgb {
+        let rgb = [0, 1, 2].map(|i| pixel[i] as f32 / 255.0);
+        let luma = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        if luma <= self.threshold {
+            return [0.0; 3];
+        }
+        rgb.map(|c| c * (luma - self.threshold) / luma)
+    }
+}
+
+/// Convolve the line of `out.len()` colors that `sample` reads with `kernel` into `out`, as if
+/// black past either end.
+fn blur_line(kernel: &[f32], sample: impl Fn(usize) -> Rgb, out: &mut [Rgb]) {
+    let (reach, len) = (kernel.len() - 1, out.len());
+    for (i, dst) in out.iter_mut().enumerate() {
+        let mut sum = [0.0; 3];
+        for j in i.saturating_sub(reach)..(i + reach + 1).min(len) {
+            let w = kernel[i.abs_diff(j)];
+            for (s, c) in sum.iter_mut().zip(sample(j)) {
+                *s += w * c;
+            }
+        }
+        *dst = sum;
+    }
+}
+
+/// One side of a normalized Gaussian kernel with standard deviation `sigma`, from the center
+/// weight out to three deviations.
+fn gaussian(sigma: f32) -> Vec<f32> {
+    let reach = (sigma * 3.0).ceil() as usize;
+    let weights: Vec<f32> = (0..=reach).map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_bright_pixels_spread_glow() {
+        let (width, height) = (41, 41);
+        let mut frame = vec![0x40; width * height * 4];
+        let center = (20 * width + 20) * 4;
+        frame[center..center + 4].copy_from_slice(&[0xff; 4]);
+        Bloom { threshold: 0.5, radius: 3.0 }.apply(&mut frame, width as u32);
+
+        let at = |x: usize, y: usize| frame[(y * width + x) * 4];
+        // Dim pixels alone leave the frame as it was, and the glow fades with distance the same
+        // way in every direction
+        assert_eq!(at(0, 0), 0x40);
+        assert!(at(21, 20) > at(23, 20) && at(23, 20) > 0x40);
+        let ring = [at(23, 20), at(20, 23), at(17, 20), at(20, 17)];
+        assert!(ring.iter().all(|&c| c == ring[0]), "{ring:?}");
+    }
+
+    #[test]
+    fn kernel_sums_to_one() {
+        let kernel = gaussian(2.5);
+        let total = kernel[0] + 2.0 * kernel[1..].iter().sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5);
+    }
+}
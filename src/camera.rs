@@ -0,0 +1,107 @@
+/// Zoom limits, keeping the view from collapsing to a point or dividing by a vanishing zoom.
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 10.0;
+
+/// Normalized coordinates of the pixel (xi, yi) on a `width`×`height` frame: the center is the
+/// origin and the shorter axis spans -1 to 1, with the longer one extending past that. Both axes
+/// share one scale, so distances mean the same thing horizontally and vertically whatever the
+/// aspect ratio. y points down, as on screen.
+pub fn normalized(xi: f32, yi: f32, width: f32, height: f32) -> (f32, f32) {
+    let scale = unit(width, height);
+    ((xi - width / 2.0) * scale, (yi - height / 2.0) * scale)
+}
+
+/// Length of one pixel in `normalized` coordinates on a `width`×`height` frame.
+fn unit(width: f32, height: f32) -> f32 {
+    2.0 / width.min(height)
+}
+
+/// Maps frame pixels to world coordinates, which is where every position in a scene lives.
+///
+/// Both the frame and the world (the scene's own `width`×`height`) are seen through
+/// `normalized` coordinates, so the scene is scaled uniformly to fit its shorter axis into the
+/// frame's, staying undistorted at any resolution. On top of that, `offset` is how far the view
+/// has been panned, in world units, and `zoom` magnifies it about the center of the frame.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub offset: (f32, f32),
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self { offset: (0.0, 0.0), zoom: 1.0 }
+    }
+}
+
+impl Camera {
+    /// World position of the top-left corner of a `frame` of (width, height) pixels looking into a
+    /// `world` of (width, height) units, and the world distance between neighboring pixels: pixel
+    /// (xi, yi) shows `origin + (xi, yi) * scale`.
+    pub fn transform(&self, frame: (f32, f32), world: (f32, f32)) -> ((f32, f32), f32) {
+        let scale = unit(frame.0, frame.1) / unit(world.0, world.1) / self.zoom;
+        (self.to_world(0.0, 0.0, frame, world), scale)
+    }
+
+    /// World position of the frame pixel (xi, yi): its `normalized` position on the frame, taken
+    /// back out of the world's normalized coordinates after panning and zooming.
+    pub fn to_world(&self, xi: f32, yi: f32, frame: (f32, f32), world: (f32, f32)) -> (f32, f32) {
+        let (nx, ny) = normalized(xi, yi, frame.0, frame.1);
+        let scale = 1.0 / (unit(world.0, world.1) * self.zoom);
+        (world.0 / 2.0 + self.offset.0 + nx * scale, world.1 / 2.0 + self.offset.1 + ny * scale)
+    }
+
+    /// Move the view by (dx, dy) world units.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.offset.0 += dx;
+        self.offset.1 += dy;
+    }
+
+    /// Scale the zoom by `factor`, within `MIN_ZOOM..=MAX_ZOOM`.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: (f32, f32) = (1280.0, 720.0);
+
+    #[test]
+    fn normalized_spans_the_shorter_axis() {
+        assert_eq!(normalized(640.0, 0.0, 1280.0, 720.0), (0.0, -1.0));
+        assert_eq!(normalized(1280.0, 720.0, 1280.0, 720.0), (640.0 / 360.0, 1.0));
+        assert_eq!(normalized(0.0, 500.0, 400.0, 1000.0), (-1.0, 0.0));
+    }
+
+    #[test]
+    fn default_camera_maps_pixels_to_themselves() {
+        assert_eq!(Camera::default().to_world(123.0, 45.0, SIZE, SIZE), (123.0, 45.0));
+    }
+
+    #[test]
+    fn other_resolutions_scale_the_world_uniformly() {
+        let camera = Camera::default();
+        for frame in [(640.0, 360.0), (333.0, 200.0), (1000.0, 1000.0), (500.0, 1200.0)] {
+            let (cx, cy) = camera.to_world(frame.0 / 2.0, frame.1 / 2.0, frame, SIZE);
+            assert_eq!((cx, cy), (640.0, 360.0), "frame {frame:?}");
+            let (rx, _) = camera.to_world(frame.0 / 2.0 + 10.0, frame.1 / 2.0, frame, SIZE);
+            let (_, dy) = camera.to_world(frame.0 / 2.0, frame.1 / 2.0 + 10.0, frame, SIZE);
+            assert!(((rx - cx) - (dy - cy)).abs() < 1e-3, "frame {frame:?} is stretched");
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_the_frame_center_in_place() {
+        let mut camera = Camera { offset: (30.0, -20.0), zoom: 1.0 };
+        let center = camera.to_world(640.0, 360.0, SIZE, SIZE);
+        camera.zoom_by(4.0);
+        assert_eq!(camera.to_world(640.0, 360.0, SIZE, SIZE), center);
+        assert_eq!(camera.to_world(644.0, 360.0, SIZE, SIZE), (center.0 + 1.0, center.1));
+
+        camera.zoom_by(1.0e-6);
+        assert_eq!(camera.zoom, MIN_ZOOM);
+    }
+}
@@ -0,0 +1,229 @@
+use clap::{Parser, ValueEnum};
+use raytracing_rs::sweep::Sweep;
+use raytracing_rs::{bloom, export, vignette};
+use std::path::{Path, PathBuf};
+
+/// GPU-accelerated, multi-threaded 2D ray tracing demo.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// JSON scene file to render instead of the built-in scene
+    pub scene: Option<PathBuf>,
+
+    /// Frame width in pixels, instead of the scene's own; the scene is scaled to fit
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Frame height in pixels, instead of the scene's own; the scene is scaled to fit
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Replace the scene's circles with a grid of copies of its first, given as COLSxROWS, e.g.
+    /// 8x4, for testing how rendering scales with many shapes
+    #[arg(long, value_parser = grid_size)]
+    pub circles: Option<(u32, u32)>,
+
+    /// Distance between circle centers in a --circles grid
+    #[arg(long, default_value_t = 100.0, value_parser = positive, requires = "circles")]
+    pub circle_spacing: f32,
+
+    /// Height of a floor line the circles reflect in and darken near, overriding the scene's
+    #[arg(long, allow_negative_numbers = true)]
+    pub floor: Option<f32>,
+
+    /// How strongly the --floor reflects the circles, from 0 to 1
+    #[arg(long, default_value_t = 0.3, value_parser = fraction, requires = "floor")]
+    pub floor_reflection: f32,
+
+    /// Have circles wrap around from one edge to the opposite one instead of bouncing
+    #[arg(long)]
+    pub wrap: bool,
+
+    /// Reflections followed off mirror circles, overriding the scene's
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=16))]
+    pub max_bounces: Option<u32>,
+
+    /// PNG or JPEG image to stretch over the scene as its background
+    #[cfg(feature = "image")]
+    #[arg(long, value_name = "PATH")]
+    pub background: Option<PathBuf>,
+
+    /// Share of the way to the mouse a dragged light or circle moves each 1/60 s, for a trailing
+    /// feel; 1 follows the mouse exactly
+    #[arg(long, default_value_t = 1.0, value_parser = unit_fraction)]
+    pub drag_smoothing: f32,
+
+    /// Spacing of the grid dragged lights and circles snap to with X, in scene pixels
+    #[arg(long, default_value_t = 20.0, value_parser = positive)]
+    pub snap_size: f32,
+
+    /// Start with bloom on, as the B key toggles: a glow around the brightest parts of the frame
+    #[arg(long)]
+    pub bloom: bool,
+
+    /// Luminance from 0 to 1 above which bloom makes pixels glow
+    #[arg(long, default_value_t = bloom::THRESHOLD, value_parser = unit_fraction)]
+    pub bloom_threshold: f32,
+
+    /// How far bloom's glow spreads: its Gaussian's standard deviation in pixels
+    #[arg(long, default_value_t = bloom::RADIUS, value_parser = positive)]
+    pub bloom_radius: f32,
+
+    /// Start with the vignette on, as the V key toggles: darkening toward the corners
+    #[arg(long)]
+    pub vignette: bool,
+
+    /// Share of their color the corners lose to the vignette
+    #[arg(long, default_value_t = vignette::STRENGTH, value_parser = unit_fraction)]
+    pub vignette_strength: f32,
+
+    /// Share of the way from the center to a corner where the vignette starts
+    #[arg(long, default_value_t = vignette::RADIUS, value_parser = below_one)]
+    pub vignette_radius: f32,
+
+    /// Start with trails on, as the W key toggles: moving things leave a fading streak
+    #[arg(long)]
+    pub trails: bool,
+
+    /// Share of the last frame each frame keeps with trails on; higher makes longer trails
+    #[arg(long, default_value_t = 0.85, value_parser = below_one)]
+    pub trail_decay: f32,
+
+    /// Start with the first light circling the middle of the scene, as the O key does
+    #[arg(long)]
+    pub orbit: bool,
+
+    /// Distance from the middle of the scene orbiting lights keep, instead of where they start
+    #[arg(long, value_parser = positive)]
+    pub orbit_radius: Option<f32>,
+
+    /// How fast orbiting lights go round, in degrees per second; negative goes anticlockwise
+    #[arg(long, default_value_t = 45.0, allow_negative_numbers = true)]
+    pub orbit_speed: f32,
+
+    /// Window title
+    #[arg(long, default_value = "Raytracing ")]
+    pub title: String,
+
+    /// Where the scene is shaded
+    #[arg(long, value_enum, default_value_t = Renderer::Gpu)]
+    pub renderer: Renderer,
+
+    /// Present frames as fast as they render instead of waiting for vsync, e.g. for benchmarking
+    #[arg(long)]
+    pub uncapped: bool,
+
+    /// Limit the frame rate, sleeping between frames to save power
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_fps: Option<u32>,
+
+    /// Render on this many threads instead of one per core
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub threads: Option<u32>,
+
+    /// Append a row of FPS, CPU and memory stats to this CSV file at every stats update
+    #[arg(long, value_name = "PATH", conflicts_with = "headless")]
+    pub metrics_csv: Option<PathBuf>,
+
+    /// Render to PNG or PPM files on the CPU without opening a window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Number of frames to render in headless mode
+    #[arg(long, default_value_t = 60, requires = "headless")]
+    pub frames: u32,
+
+    /// Directory to write headless frames to, created if missing
+    #[arg(long, default_value = "frames", requires = "headless")]
+    pub out: PathBuf,
+
+    /// Render a still frame at each step of a parameter instead of simulating, as
+    /// `<param>:<from>:<to>:<steps>` with param light-x, light-y or light-r (the first light's)
+    #[arg(long, value_name = "SPEC", requires = "headless", conflicts_with = "frames")]
+    pub sweep: Option<Sweep>,
+
+    /// File format of headless frames
+    #[arg(long, value_enum, default_value_t = Format::default(), requires = "headless")]
+    pub format: Format,
+}
+
+/// Format frames and screenshots are saved in. PNG is the default, or PPM, the only one there
+/// is, without the `image` feature.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[cfg(feature = "image")]
+    #[default]
+    Png,
+    /// Binary PPM, bigger but with no encoding to wait on
+    #[cfg_attr(not(feature = "image"), default)]
+    Ppm,
+}
+
+impl Format {
+    /// File extension for the format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            #[cfg(feature = "image")]
+            Format::Png => "png",
+            Format::Ppm => "ppm",
+        }
+    }
+
+    /// Write an RGBA `frame` to `path` in this format.
+    pub fn save(self, frame: &[u8], width: u32, height: u32, path: &Path) -> Result<(), export::Error> {
+        match self {
+            #[cfg(feature = "image")]
+            Format::Png => export::save_png(frame, width, height, path),
+            Format::Ppm => Ok(export::save_ppm(frame, width, height, path)?),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Renderer {
+    /// WGSL fragment shader on the GPU adapter
+    Gpu,
+    /// Rayon over the CPU cores, for adapters that can't run the shader
+    Cpu,
+}
+
+impl Renderer {
+    /// Lowercase name, as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Renderer::Gpu => "gpu",
+            Renderer::Cpu => "cpu",
+        }
+    }
+}
+
+/// Parse a grid size written `COLSxROWS`, both at least 1.
+fn grid_size(s: &str) -> Result<(u32, u32), String> {
+    let (cols, rows) = s.split_once('x').ok_or_else(|| format!("{s:?} is not COLSxROWS, e.g. 8x4"))?;
+    let count = |n: &str| n.parse::<u32>().ok().filter(|&n| n > 0).ok_or_else(|| format!("{n:?} is not a positive count"));
+    Ok((count(cols)?, count(rows)?))
+}
+
+/// Parse a fraction in `0 <= f <= 1`.
+fn fraction(s: &str) -> Result<f32, String> {
+    let f: f32 = s.parse().map_err(|err| format!("{err}"))?;
+    if (0.0..=1.0).contains(&f) { Ok(f) } else { Err(format!("{f} is not between 0 and 1")) }
+}
+
+/// Parse a fraction in `0 <= f < 1`.
+fn below_one(s: &str) -> Result<f32, String> {
+    let f: f32 = s.parse().map_err(|err| format!("{err}"))?;
+    if (0.0..1.0).contains(&f) { Ok(f) } else { Err(format!("{f} is not at least 0 and below 1")) }
+}
+
+/// Parse a fraction in `0 < f <= 1`.
+fn unit_fraction(s: &str) -> Result<f32, String> {
+    let f: f32 = s.parse().map_err(|err| format!("{err}"))?;
+    if f > 0.0 && f <= 1.0 { Ok(f) } else { Err(format!("{f} is not above 0 and at most 1")) }
+}
+
+/// Parse a finite number above 0.
+fn positive(s: &str) -> Result<f32, String> {
+    let f: f32 = s.parse().map_err(|err| format!("{err}"))?;
+    if f > 0.0 && f.is_finite() { Ok(f) } else { Err(format!("{f} is not a positive number")) }
+}
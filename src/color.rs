@@ -0,0 +1,79 @@
+/// RGB color with each channel in `0.0..=1.0`.
+pub type Rgb = [f32; 3];
+
+pub const WHITE: Rgb = [1.0, 1.0, 1.0];
+pub const BLACK: Rgb = [0.0, 0.0, 0.0];
+
+/// Convert an 8-bit color to unit floats.
+#[inline]
+pub fn from_u8(color: [u8; 3]) -> Rgb {
+    color.map(|c| c as f32 / 255.0)
+}
+
+/// Convert unit floats to an opaque RGBA pixel, clamping out-of-range channels.
+#[inline]
+pub fn to_rgba8(color: Rgb) -> [u8; 4] {
+    let [r, g, b] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    [r, g, b, 0xff]
+}
+
+/// Order in which ordered dithering lights the pixels of each 4×4 block as a level rises from
+/// one 8-bit step to the next.
+const BAYER4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// `to_rgba8` for the pixel at (x, y) with ordered dithering: before rounding, each channel is
+/// nudged by up to half a step either way following `BAYER4`, so a slope too shallow for 8 bits
+/// comes out as a fine mix of the two nearest levels instead of flat bands.
+#[inline]
+pub fn to_rgba8_dithered(color: Rgb, x: usize, y: usize) -> [u8; 4] {
+    let offset = (BAYER4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+    let [r, g, b] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0 + offset).round() as u8);
+    [r, g, b, 0xff]
+}
+
+/// Encode a linear channel with the piecewise sRGB transfer curve.
+#[inline]
+pub fn srgb_encode(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decode an sRGB channel back to linear light, the inverse of `srgb_encode`.
+#[inline]
+pub fn srgb_decode(c: f32) -> f32 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Compress a linear HDR channel from `0.0..` into `0.0..1.0`, using Krzysztof Narkowicz's fit
+/// of the ACES filmic curve. Overlapping lights roll off smoothly into their own hue instead of
+/// every channel clipping to white, at the cost of slightly more contrast in the midtones.
+#[inline]
+pub fn aces(c: f32) -> f32 {
+    let c = c.max(0.0);
+    (c * (2.51 * c + 0.03) / (c * (2.43 * c + 0.59) + 0.14)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dithering_keeps_the_level_on_average() {
+        // A quarter of the way from 100 to 101 lights a quarter of each block at 101
+        let level = 100.25 / 255.0;
+        let block: Vec<u8> = (0..16).map(|i| to_rgba8_dithered([level; 3], i % 4, i / 4)[0]).collect();
+        assert!(block.iter().all(|&v| v == 100 || v == 101));
+        assert_eq!(block.iter().filter(|&&v| v == 101).count(), 4);
+        // Exact levels, black and white among them, come out unchanged
+        for v in [0, 37, 255] {
+            assert!((0..16).all(|i| to_rgba8_dithered([v as f32 / 255.0; 3], i % 4, i / 4)[0] == v));
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Everything that can stop the binary from starting up, or end its event loop.
+
+use raytracing_rs::scene::SceneError;
+use thiserror::Error;
+use winit::error::{EventLoopError, OsError};
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("could not load the scene")]
+    Scene(#[from] SceneError),
+    #[cfg(feature = "image")]
+    #[error("could not load the background image")]
+    Background(#[source] image::ImageError),
+    #[error("could not start the render threads")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    #[error("headless render failed")]
+    Headless(#[source] raytracing_rs::export::Error),
+    #[error("could not start the async runtime")]
+    Runtime(#[source] std::io::Error),
+    #[error("could not create the event loop")]
+    EventLoop(#[source] EventLoopError),
+    #[error("could not create the window")]
+    Window(#[from] OsError),
+    #[error("could not set up the pixel buffer")]
+    Pixels(#[from] pixels::Error),
+    #[error("event loop failed")]
+    Run(#[source] EventLoopError),
+}
@@ -0,0 +1,94 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::scene::Scene;
+use crate::world::{RenderSettings, World};
+
+/// What can go wrong saving a frame: encoding as well as I/O errors with the `image` feature, and
+/// just I/O without it, when only PPM can be written.
+#[cfg(feature = "image")]
+pub type Error = image::ImageError;
+#[cfg(not(feature = "image"))]
+pub type Error = io::Error;
+
+/// Write an RGBA frame buffer to `path` as a PNG.
+#[cfg(feature = "image")]
+pub fn save_png(frame: &[u8], width: u32, height: u32, path: &Path) -> image::ImageResult<()> {
+    image::save_buffer(path, frame, width, height, image::ExtendedColorType::Rgba8)
+}
+
+/// Write an RGBA frame buffer to `path` as a binary (P6) PPM, dropping the alpha channel. Unlike
+/// PNG that needs no encoder, so it's there without the `image` feature, and most image viewers
+/// still open it.
+pub fn save_ppm(frame: &[u8], width: u32, height: u32, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "P6\n{width} {height}\n255\n")?;
+    for pixel in frame.chunks_exact(4) {
+        out.write_all(&pixel[..3])?;
+    }
+    out.flush()
+}
+
+/// `<prefix>-<unix millis>.<ext>` in the working directory, so repeated captures don't overwrite.
+pub fn timestamped_path(prefix: &str, ext: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    PathBuf::from(format!("{prefix}-{millis}.{ext}"))
+}
+
+/// A screenshot's sidecar: the scene as it was, fields and all, so the file loads as a scene
+/// again, with the render settings alongside.
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    #[serde(flatten)]
+    scene: Scene,
+    render: RenderSettings,
+    /// Which renderer drew the frame, e.g. "cpu" or "gpu".
+    renderer: &'a str,
+}
+
+/// Write `world`'s current scene and render settings next to the image at `image`, as JSON under
+/// the same name with a `.json` extension, so a screenshot can be reproduced later. Returns the
+/// sidecar's path.
+pub fn save_sidecar(image: &Path, world: &World, renderer: &str) -> io::Result<PathBuf> {
+    let sidecar = Sidecar { scene: world.to_scene(), render: world.render_settings(), renderer };
+    let path = image.with_extension("json");
+    fs::write(&path, serde_json::to_string_pretty(&sidecar)?)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_holds_a_header_and_the_rgb_bytes() {
+        let path = std::env::temp_dir().join(format!("raytracing-rs-{}.ppm", std::process::id()));
+        let frame = [1, 2, 3, 0xff, 4, 5, 6, 0xff];
+        save_ppm(&frame, 2, 1, &path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sidecars_load_back_as_the_scene() {
+        let mut world = World::new(Scene::default());
+        world.step(0.5);
+        let dir = std::env::temp_dir().join(format!("raytracing-rs-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sidecar = save_sidecar(&dir.join("shot.png"), &world, "cpu").unwrap();
+        assert_eq!(sidecar, dir.join("shot.json"));
+
+        let scene = Scene::load(&sidecar).expect("sidecar should load as a scene");
+        assert_eq!(scene.circles[0].y, world.to_scene().circles[0].y);
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&sidecar).unwrap()).unwrap();
+        assert_eq!(json["render"]["time"], 0.5);
+        assert_eq!(json["render"]["view"], "shaded");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
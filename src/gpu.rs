@@ -0,0 +1,449 @@
+#[cfg(feature = "image")]
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use pixels::Pixels;
+
+use crate::light::{blue_noise, BLUE_NOISE_MAX};
+use crate::occluder::SdfShape;
+use crate::world::View;
+use crate::World;
+
+const FLAG_GAMMA: u32 = 1;
+const FLAG_TINT_LIGHTS: u32 = 2;
+const FLAG_SRGB_TARGET: u32 = 4;
+const FLAG_SRGB_OVERLAY: u32 = 8;
+const FLAG_PENUMBRA: u32 = 16;
+#[cfg(feature = "image")]
+const FLAG_BACKGROUND_IMAGE: u32 = 32;
+const FLAG_TONEMAP: u32 = 64;
+const FLAG_DITHER: u32 = 128;
+const FLAG_NORMALS: u32 = 256;
+const FLAG_EDGE_AA: u32 = 512;
+const FLAG_VIGNETTE: u32 = 1024;
+const FLAG_FLOOR: u32 = 2048;
+const FLAG_INVERT: u32 = 4096;
+
+/// Mirrors `Params` in `shaders/scene.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Params {
+    clip: [f32; 4],
+    ambient: [f32; 4],
+    misc: [f32; 4],
+    counts: [u32; 4],
+    options: [u32; 4],
+    bounces: [u32; 4],
+    camera: [f32; 4],
+    gradient: [[f32; 4]; 2],
+    fill: [f32; 4],
+    vignette: [f32; 4],
+    floor: [f32; 4],
+}
+
+/// A read-only storage buffer that grows to fit whatever the scene needs.
+struct StorageBuffer {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+}
+
+impl StorageBuffer {
+    fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        Self { label, buffer: Self::create(device, label, 16) }
+    }
+
+    fn create(device: &wgpu::Device, label: &'static str, size: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) {
+        // Empty bindings aren't allowed, so the buffer always keeps its initial 16 bytes
+        if data.len() as u64 > self.buffer.size() {
+            self.buffer = Self::create(device, self.label, (data.len() as u64).next_power_of_two());
+        }
+        queue.write_buffer(&self.buffer, 0, data);
+    }
+}
+
+/// Renders the scene with a fragment shader straight onto the `pixels` surface; see
+/// `shaders/scene.wgsl`. The CPU frame buffer is still uploaded and composited on top, so
+/// anything drawn into it with transparent pixels around (the stats overlay) stays visible.
+pub struct GpuRenderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params: wgpu::Buffer,
+    lights: StorageBuffer,
+    circles: StorageBuffer,
+    rects: StorageBuffer,
+    walls: StorageBuffer,
+    polygons: StorageBuffer,
+    verts: StorageBuffer,
+    /// First shape in `sdf_shapes`, shape count, steps and epsilon of each `Sdf`.
+    sdfs: StorageBuffer,
+    sdf_shapes: StorageBuffer,
+    /// `light::blue_noise` for the shadow sample count, if it has a set.
+    disk_samples: StorageBuffer,
+    /// `World::background`, uploaded on the first frame that has one.
+    background: Option<wgpu::Texture>,
+    /// Bound in place of `background` until then, since the binding can't be left empty.
+    placeholder: wgpu::Texture,
+    flags: u32,
+}
+
+impl GpuRenderer {
+    pub fn new(pixels: &Pixels) -> Self {
+        let device = pixels.device();
+        let module = device.create_shader_module(wgpu::include_wgsl!("shaders/scene.wgsl"));
+
+        let storage = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("scene_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1),
+                storage(2),
+                storage(3),
+                storage(4),
+                storage(5),
+                storage(6),
+                storage(9),
+                storage(10),
+                storage(11),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("scene_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let format = pixels.render_texture_format();
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scene_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("scene_params"),
+            size: std::mem::size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut flags = 0;
+        if format.is_srgb() {
+            flags |= FLAG_SRGB_TARGET;
+        }
+        if pixels.context().texture_format.is_srgb() {
+            flags |= FLAG_SRGB_OVERLAY;
+        }
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            params,
+            lights: StorageBuffer::new(device, "scene_lights"),
+            circles: StorageBuffer::new(device, "scene_circles"),
+            rects: StorageBuffer::new(device, "scene_rects"),
+            walls: StorageBuffer::new(device, "scene_walls"),
+            polygons: StorageBuffer::new(device, "scene_polygons"),
+            verts: StorageBuffer::new(device, "scene_verts"),
+            sdfs: StorageBuffer::new(device, "scene_sdfs"),
+            sdf_shapes: StorageBuffer::new(device, "scene_sdf_shapes"),
+            disk_samples: StorageBuffer::new(device, "scene_disk_samples"),
+            background: None,
+            placeholder: upload_texture(device, pixels.queue(), "scene_background_placeholder", 1, 1, &[255; 4]),
+            flags,
+        }
+    }
+
+    /// Upload `world` and draw it, with the current contents of `pixels.frame()` on top.
+    pub fn render(&mut self, world: &World, pixels: &Pixels) -> Result<(), pixels::Error> {
+        let device = pixels.device();
+        let queue = pixels.queue();
+
+        let lights: Vec<[f32; 16]> = world.lights.iter()
+            .zip(&world.light_levels)
+            .map(|(l, &level)| {
+                let [r, g, b] = crate::color::from_u8(l.color);
+                let [sr, sg, sb] = crate::color::from_u8(l.ambient);
+                // A half angle of 180° lets everything through, as with no cone
+                let (direction, half_angle) = l.cone.map_or((0.0, 180.0), |c| (c.direction, c.half_angle.min(180.0)));
+                [
+                    l.x, l.y, l.r, level, r, g, b, 0.0, direction.to_radians(), half_angle.to_radians(), 0.0, 0.0,
+                    sr, sg, sb, 0.0,
+                ]
+            })
+            .collect();
+        let circles: Vec<[f32; 12]> = world.circles.iter()
+            .map(|c| {
+                let m = &c.material;
+                let [ar, ag, ab] = crate::color::from_u8(m.albedo);
+                let [er, eg, eb] = crate::color::from_u8(m.emissive);
+                [c.x, c.y, c.r, m.shininess, ar, ag, ab, m.reflectivity, er, eg, eb, c.opacity]
+            })
+            .collect();
+        let rects: Vec<[f32; 4]> = world.scene.rects.iter().map(|r| [r.x, r.y, r.w, r.h]).collect();
+        let walls: Vec<[f32; 4]> = world.walls.iter().map(|w| [w.a.0, w.a.1, w.b.0, w.b.1]).collect();
+        let mut polygons: Vec<[u32; 2]> = Vec::new();
+        let mut verts: Vec<[f32; 2]> = Vec::new();
+        for polygon in &world.scene.polygons {
+            polygons.push([verts.len() as u32, polygon.verts.len() as u32]);
+            verts.extend(polygon.verts.iter().map(|&(x, y)| [x, y]));
+        }
+
+        // Every SDF shape goes up as a rounded rect, center and half size then corner radius; a
+        // circle is one rounded all the way round
+        let mut sdfs: Vec<[u32; 4]> = Vec::new();
+        let mut sdf_shapes: Vec<[f32; 8]> = Vec::new();
+        for sdf in &world.scene.sdfs {
+            sdfs.push([sdf_shapes.len() as u32, sdf.shapes.len() as u32, sdf.steps, sdf.epsilon.to_bits()]);
+            sdf_shapes.extend(sdf.shapes.iter().map(|shape| match *shape {
+                SdfShape::Circle { x, y, r } => [x, y, r, r, r, 0.0, 0.0, 0.0],
+                SdfShape::RoundedRect { x, y, w, h, radius } => {
+                    [x, y, w / 2.0, h / 2.0, radius.clamp(0.0, w.min(h) / 2.0), 0.0, 0.0, 0.0]
+                }
+            }));
+        }
+
+        self.lights.write(device, queue, bytemuck::cast_slice(&lights));
+        self.circles.write(device, queue, bytemuck::cast_slice(&circles));
+        self.rects.write(device, queue, bytemuck::cast_slice(&rects));
+        self.walls.write(device, queue, bytemuck::cast_slice(&walls));
+        self.polygons.write(device, queue, bytemuck::cast_slice(&polygons));
+        self.verts.write(device, queue, bytemuck::cast_slice(&verts));
+        self.sdfs.write(device, queue, bytemuck::cast_slice(&sdfs));
+        self.sdf_shapes.write(device, queue, bytemuck::cast_slice(&sdf_shapes));
+        if (2..=BLUE_NOISE_MAX).contains(&world.shadow_samples) {
+            let disk: Vec<[f32; 2]> = blue_noise(world.shadow_samples).iter().map(|&(x, y)| [x, y]).collect();
+            self.disk_samples.write(device, queue, bytemuck::cast_slice(&disk));
+        }
+
+        let context = pixels.context();
+        let (cx, cy, cw, ch) = context.scaling_renderer.clip_rect();
+        let mut flags = self.flags;
+        if world.gamma_correct {
+            flags |= FLAG_GAMMA;
+        }
+        if world.tint_light_disks {
+            flags |= FLAG_TINT_LIGHTS;
+        }
+        if world.tonemap {
+            flags |= FLAG_TONEMAP;
+        }
+        if world.dither {
+            flags |= FLAG_DITHER;
+        }
+        if world.edge_aa {
+            flags |= FLAG_EDGE_AA;
+        }
+        if world.vignette {
+            flags |= FLAG_VIGNETTE;
+        }
+        if world.scene.floor.is_some() {
+            flags |= FLAG_FLOOR;
+        }
+        if world.invert {
+            flags |= FLAG_INVERT;
+        }
+        match world.view {
+            View::Shaded => {}
+            View::Penumbra => flags |= FLAG_PENUMBRA,
+            View::Normals => flags |= FLAG_NORMALS,
+        }
+        #[cfg(feature = "image")]
+        if let Some(image) = &world.background {
+            flags |= FLAG_BACKGROUND_IMAGE;
+            if self.background.is_none() {
+                // Larger pictures than the device takes get shrunk to fit; they're stretched anyway
+                let max = device.limits().max_texture_dimension_2d;
+                let (width, height) = image.dimensions();
+                let fit = if width > max || height > max {
+                    let scale = max as f32 / width.max(height) as f32;
+                    let (w, h) = (((width as f32 * scale) as u32).max(1), ((height as f32 * scale) as u32).max(1));
+                    Cow::Owned(image::imageops::thumbnail(image, w, h))
+                } else {
+                    Cow::Borrowed(image)
+                };
+                let (width, height) = fit.dimensions();
+                self.background = Some(upload_texture(device, queue, "scene_background", width, height, &fit));
+            }
+        }
+        let [ar, ag, ab] = crate::color::from_u8(world.ambient);
+        let ((ox, oy), scale) = world.view_transform();
+        let params = Params {
+            clip: [cx as f32, cy as f32, cw as f32, ch as f32],
+            ambient: [ar, ag, ab, 0.0],
+            misc: [world.width as f32, world.height as f32, world.falloff, cw as f32 / world.width as f32],
+            counts: [lights.len() as u32, circles.len() as u32, rects.len() as u32, walls.len() as u32],
+            options: [polygons.len() as u32, world.shadow_samples, world.aa, flags],
+            bounces: [world.max_bounces, sdfs.len() as u32, 0, 0],
+            camera: [ox, oy, scale, 0.0],
+            gradient: {
+                let [top, bottom] = world.gradient.unwrap_or([[255; 3]; 2]).map(crate::color::from_u8);
+                let (width, height) = (world.scene.width as f32, world.scene.height as f32);
+                [[top[0], top[1], top[2], width], [bottom[0], bottom[1], bottom[2], height]]
+            },
+            fill: {
+                let [r, g, b] = crate::color::from_u8(world.fill_color);
+                [r, g, b, 0.0]
+            },
+            vignette: [world.vignette_settings.strength, world.vignette_settings.radius, 0.0, 0.0],
+            floor: world.scene.floor.map_or([0.0; 4], |f| [f.y, f.reflection, 0.0, 0.0]),
+        };
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        let overlay = context.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let background = self.background.as_ref().unwrap_or(&self.placeholder)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scene_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: self.params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: self.lights.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: self.circles.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: self.rects.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: self.walls.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 5, resource: self.polygons.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 6, resource: self.verts.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 7, resource: wgpu::BindingResource::TextureView(&overlay) },
+                wgpu::BindGroupEntry { binding: 8, resource: wgpu::BindingResource::TextureView(&background) },
+                wgpu::BindGroupEntry { binding: 9, resource: self.disk_samples.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 10, resource: self.sdfs.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 11, resource: self.sdf_shapes.buffer.as_entire_binding() },
+            ],
+        });
+
+        pixels.render_with(|encoder, render_target, _context| {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("scene_render_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: render_target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_scissor_rect(cx, cy, cw, ch);
+            pass.draw(0..3, 0..1);
+            Ok(())
+        })
+    }
+}
+
+/// A `width`×`height` RGBA texture holding `texels`, uninterpreted by the sampler (`Rgba8Unorm`
+/// rather than sRGB) so the shader linearizes them itself, like every other scene color.
+fn upload_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &'static str,
+    width: u32,
+    height: u32,
+    texels: &[u8],
+) -> wgpu::Texture {
+    let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        texels,
+        wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(4 * width), rows_per_image: Some(height) },
+        size,
+    );
+    texture
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn scene_shader_validates() {
+        use wgpu::naga;
+        let module = naga::front::wgsl::parse_str(include_str!("shaders/scene.wgsl"))
+            .unwrap_or_else(|err| panic!("{}", err.emit_to_string(include_str!("shaders/scene.wgsl"))));
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+            .validate(&module)
+            .expect("scene.wgsl failed validation");
+    }
+}
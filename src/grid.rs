@@ -0,0 +1,215 @@
+/// Uniform grid bucketing shapes by bounding box, so a shadow ray only needs to test the shapes
+/// in the cells it passes through.
+///
+/// Shapes are referred to by index into whatever list the grid was built from. Each bounding box
+/// is padded by `PAD` before bucketing, so rays that graze a box or pass exactly through a cell
+/// corner still visit a cell holding it despite rounding in the traversal.
+pub struct Grid {
+    origin: (f32, f32),
+    inv_cell: f32,
+    cols: usize,
+    rows: usize,
+    /// Start of each cell's run in `items`, plus one past the end of the last.
+    starts: Vec<u32>,
+    items: Vec<u32>,
+}
+
+const PAD: f32 = 1.0;
+
+/// Most cells along either axis. Past this the cells grow instead, so a scene with shapes far off
+/// in the distance doesn't allocate a grid sized to reach them.
+const MAX_CELLS: usize = 256;
+
+impl Grid {
+    /// Bucket `bounds` (`[min x, min y, max x, max y]` per shape) into square cells of size
+    /// `cell`, covering at least `area` and every box. Empty or non-finite boxes are left out, and
+    /// the cells are made larger if need be to keep to `MAX_CELLS` a side.
+    pub fn new(cell: f32, area: [f32; 4], bounds: &[[f32; 4]]) -> Self {
+        let valid = |b: &[f32; 4]| b.iter().all(|v| v.is_finite()) && b[0] <= b[2] && b[1] <= b[3];
+        let [mut x0, mut y0, mut x1, mut y1] = area;
+        for b in bounds.iter().filter(|b| valid(b)) {
+            x0 = x0.min(b[0] - PAD);
+            y0 = y0.min(b[1] - PAD);
+            x1 = x1.max(b[2] + PAD);
+            y1 = y1.max(b[3] + PAD);
+        }
+        let cell = cell.max((x1 - x0).max(y1 - y0) / (MAX_CELLS - 1) as f32);
+        let cols = ((x1 - x0) / cell).floor() as usize + 1;
+        let rows = ((y1 - y0) / cell).floor() as usize + 1;
+        let mut grid = Self { origin: (x0, y0), inv_cell: 1.0 / cell, cols, rows, starts: Vec::new(), items: Vec::new() };
+
+        // Count the shapes per cell, then fill each cell's run in one pass
+        let ranges: Vec<_> = bounds.iter()
+            .map(|b| valid(b).then(|| {
+                let (c0, r0) = grid.cell_of(b[0] - PAD, b[1] - PAD);
+                let (c1, r1) = grid.cell_of(b[2] + PAD, b[3] + PAD);
+                (c0..=c1, r0..=r1)
+            }))
+            .collect();
+        let mut counts = vec![0u32; cols * rows + 1];
+        for (cs, rs) in ranges.iter().flatten() {
+            for r in rs.clone() {
+                for c in cs.clone() {
+                    counts[r * cols + c + 1] += 1;
+                }
+            }
+        }
+        for i in 1..counts.len() {
+            counts[i] += counts[i - 1];
+        }
+        grid.items = vec![0; counts[cols * rows] as usize];
+        let mut next = counts.clone();
+        for (i, (cs, rs)) in ranges.iter().enumerate().filter_map(|(i, r)| Some((i, r.as_ref()?))) {
+            for r in rs.clone() {
+                for c in cs.clone() {
+                    let slot = &mut next[r * cols + c];
+                    grid.items[*slot as usize] = i as u32;
+                    *slot += 1;
+                }
+            }
+        }
+        grid.starts = counts;
+        grid
+    }
+
+    /// Grid coordinates of (x, y), in cells from the origin.
+    #[inline]
+    fn to_grid(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.origin.0) * self.inv_cell, (y - self.origin.1) * self.inv_cell)
+    }
+
+    /// Column and row of the cell holding (x, y), clamped to the grid.
+    fn cell_of(&self, x: f32, y: f32) -> (usize, usize) {
+        let (gx, gy) = self.to_grid(x, y);
+        self.cell_at(gx, gy)
+    }
+
+    #[inline]
+    fn cell_at(&self, gx: f32, gy: f32) -> (usize, usize) {
+        let c = gx.floor().max(0.0) as usize;
+        let r = gy.floor().max(0.0) as usize;
+        (c.min(self.cols - 1), r.min(self.rows - 1))
+    }
+
+    #[inline]
+    fn inside(&self, gx: f32, gy: f32) -> bool {
+        (0.0..self.cols as f32).contains(&gx) && (0.0..self.rows as f32).contains(&gy)
+    }
+
+    #[inline]
+    fn items(&self, c: usize, r: usize) -> &[u32] {
+        let i = r * self.cols + c;
+        &self.items[self.starts[i] as usize..self.starts[i + 1] as usize]
+    }
+
+    /// Shapes whose boxes may hold (x, y), or `None` if it lies outside the grid.
+    pub fn at(&self, x: f32, y: f32) -> Option<&[u32]> {
+        let (gx, gy) = self.to_grid(x, y);
+        if !self.inside(gx, gy) {
+            return None;
+        }
+        let (c, r) = self.cell_at(gx, gy);
+        Some(self.items(c, r))
+    }
+
+    /// Call `hit` on the shapes in every cell the segment from (x0, y0) to (x1, y1) passes
+    /// through, stopping at the first that returns true. Shapes spanning several cells may be
+    /// passed more than once.
+    ///
+    /// Returns whether any shape was hit, or `None` if either end lies outside the grid.
+    pub fn any_along(&self, x0: f32, y0: f32, x1: f32, y1: f32, mut hit: impl FnMut(u32) -> bool) -> Option<bool> {
+        let (gx0, gy0) = self.to_grid(x0, y0);
+        let (gx1, gy1) = self.to_grid(x1, y1);
+        if !self.inside(gx0, gy0) || !self.inside(gx1, gy1) {
+            return None;
+        }
+
+        // Walk cell by cell (Amanatides & Woo), always stepping along whichever axis reaches its
+        // next cell boundary first, and never past the end cell so rounding can't overshoot it
+        let (mut c, mut r) = self.cell_at(gx0, gy0);
+        let (end_c, end_r) = self.cell_at(gx1, gy1);
+        if (c, r) == (end_c, end_r) {
+            return Some(self.items(c, r).iter().any(|&i| hit(i)));
+        }
+        let (inv_dx, inv_dy) = (1.0 / (gx1 - gx0), 1.0 / (gy1 - gy0));
+        let first_boundary = |g: f32, i: usize, inv_d: f32| {
+            if inv_d > 0.0 { (i as f32 + 1.0 - g) * inv_d } else { (i as f32 - g) * inv_d }
+        };
+        let mut t_x = first_boundary(gx0, c, inv_dx);
+        let mut t_y = first_boundary(gy0, r, inv_dy);
+        let (delta_x, delta_y) = (inv_dx.abs(), inv_dy.abs());
+
+        loop {
+            if self.items(c, r).iter().any(|&i| hit(i)) {
+                return Some(true);
+            }
+            if (c, r) == (end_c, end_r) {
+                return Some(false);
+            }
+            if r == end_r || (c != end_c && t_x < t_y) {
+                c = if end_c > c { c + 1 } else { c - 1 };
+                t_x += delta_x;
+            } else {
+                r = if end_r > r { r + 1 } else { r - 1 };
+                t_y += delta_y;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::occluder::{Circle, Occluder, Rect, Segment};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn grid_matches_brute_force_on_random_scene() {
+        let mut rng = StdRng::seed_from_u64(26);
+        let mut shapes: Vec<Box<dyn Occluder>> = Vec::new();
+        for _ in 0..40 {
+            let (x, y) = (rng.random_range(0.0..1280.0), rng.random_range(0.0..720.0));
+            shapes.push(match rng.random_range(0..3) {
+                0 => Box::new(Circle::new(x, y, rng.random_range(5.0..120.0), 0.0)),
+                1 => Box::new(Rect::new(x, y, rng.random_range(1.0..200.0), rng.random_range(1.0..200.0))),
+                _ => Box::new(Segment::new((x, y), (x + rng.random_range(-150.0..150.0), y + rng.random_range(-150.0..150.0)))),
+            });
+        }
+        let bounds: Vec<_> = shapes.iter().map(|s| s.bounds()).collect();
+        let grid = Grid::new(64.0, [0.0, 0.0, 1280.0, 720.0], &bounds);
+
+        for _ in 0..20 {
+            let (lx, ly) = (rng.random_range(0.0..1280.0f32).round(), rng.random_range(0.0..720.0f32).round());
+            for y in (0..720).step_by(5) {
+                for x in (0..1280).step_by(5) {
+                    let (x, y) = (x as f32, y as f32);
+                    let brute = shapes.iter().any(|s| s.blocks_ray(lx, ly, x, y));
+                    let fast = grid.any_along(lx, ly, x, y, |i| shapes[i as usize].blocks_ray(lx, ly, x, y));
+                    assert_eq!(fast, Some(brute), "light ({lx}, {ly}), pixel ({x}, {y})");
+
+                    let brute = shapes.iter().any(|s| s.contains(x, y));
+                    let fast = grid.at(x, y).unwrap().iter().any(|&i| shapes[i as usize].contains(x, y));
+                    assert_eq!(fast, brute, "inside at ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn points_outside_the_grid_are_not_answered() {
+        let grid = Grid::new(64.0, [0.0, 0.0, 100.0, 100.0], &[]);
+        assert_eq!(grid.any_along(-500.0, 0.0, 50.0, 50.0, |_| true), None);
+        assert!(grid.at(50.0, 500.0).is_none());
+        assert_eq!(grid.any_along(10.0, 10.0, 90.0, 90.0, |_| true), Some(false));
+    }
+
+    #[test]
+    fn far_off_shapes_grow_the_cells_instead_of_the_grid() {
+        let bounds = [[10.0, 10.0, 20.0, 20.0], [1e7, -1e7, 1e7 + 10.0, -1e7 + 10.0]];
+        let grid = Grid::new(1.0, [0.0, 0.0, 1280.0, 720.0], &bounds);
+        assert!(grid.cols <= MAX_CELLS && grid.rows <= MAX_CELLS, "{}x{}", grid.cols, grid.rows);
+        assert!(grid.at(15.0, 15.0).unwrap().contains(&0));
+        assert!(grid.at(1e7 + 5.0, -1e7 + 5.0).unwrap().contains(&1));
+    }
+}
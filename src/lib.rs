@@ -0,0 +1,60 @@
+//! 2D ray tracer: circles, walls and other shapes lit by disk lights in their plane, with soft
+//! shadows, reflections and colored glass.
+//!
+//! A [`Scene`] describes what's there, usually loaded from JSON, and a [`World`] brings it to
+//! life: it moves the circles, reacts to input and renders frames on the CPU, or on the GPU
+//! through `gpu::GpuRenderer`. For a single still frame, [`render`] does all of that in one call.
+//! The shadow test itself, [`is_shadowed`], works on plain coordinates and needs neither.
+//!
+//! The CPU renderer has no GUI dependencies. Window input (`World::update`) needs the `gui`
+//! feature, and `gpu::GpuRenderer` the `gpu` one; both are on by default, so depend on the crate
+//! with `default-features = false` to leave out winit and wgpu.
+
+pub mod bloom;
+pub mod camera;
+pub mod color;
+pub mod export;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+mod grid;
+pub mod light;
+pub mod material;
+pub mod occluder;
+pub mod orbit;
+pub mod overlay;
+pub mod record;
+pub mod scene;
+pub mod sweep;
+pub mod timeline;
+pub mod vignette;
+mod world;
+
+pub use occluder::{is_shadowed, Circle};
+pub use scene::Scene;
+pub use world::{RenderSettings, World};
+
+/// Render `scene` as it starts out into `frame`, an RGBA buffer of the scene's own
+/// `width`×`height` pixels, on the CPU.
+///
+/// ```
+/// use raytracing_rs::Scene;
+///
+/// let scene = Scene { width: 64, height: 36, ..Scene::default() };
+/// let mut frame = vec![0; 64 * 36 * 4];
+/// raytracing_rs::render(&scene, &mut frame);
+/// assert!(frame.chunks(4).all(|pixel| pixel[3] == 0xff));
+/// ```
+///
+/// # Panics
+///
+/// If `frame` is the wrong size.
+pub fn render(scene: &Scene, frame: &mut [u8]) {
+    assert_eq!(
+        frame.len(),
+        scene.width as usize * scene.height as usize * 4,
+        "frame must hold {}x{} RGBA pixels",
+        scene.width,
+        scene.height,
+    );
+    World::new(scene.clone()).draw(frame);
+}
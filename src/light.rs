@@ -0,0 +1,245 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+/// How far a flickering light's intensity swings either way unless the scene says otherwise, as
+/// a fraction of the intensity.
+pub const FLICKER_AMPLITUDE: f32 = 0.3;
+
+/// Largest shadow sample count with a `blue_noise` set.
+pub const BLUE_NOISE_MAX: u32 = 64;
+
+/// Point light drawn as a disk of radius `r`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Light {
+    pub x: f32,
+    pub y: f32,
+    pub r: f32,
+    pub color: [u8; 3],
+    /// Color the light leaves in its own shadows instead of its light, dimmed with distance the
+    /// same way. Black by default, so a shadow is just the light's absence.
+    #[serde(default)]
+    pub ambient: [u8; 3],
+    /// Brightness multiplier on `color`.
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+    /// Whether the intensity jitters like a candle flame, see `intensity_at`.
+    #[serde(default)]
+    pub flicker: bool,
+    #[serde(default = "default_flicker_amplitude")]
+    pub flicker_amplitude: f32,
+    /// Narrows the light to a beam, like a spotlight's; without one it shines all around.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cone: Option<Cone>,
+}
+
+/// The beam of a spotlight, in degrees. Points whose direction from the light's center is more
+/// than `half_angle` from `direction` get none of its light, whatever stands in the way.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Cone {
+    /// Clockwise from the +x axis, as y points down.
+    pub direction: f32,
+    pub half_angle: f32,
+}
+
+impl Cone {
+    /// Whether the offset (dx, dy) from the light's center lies inside the beam.
+    #[inline]
+    pub fn admits(&self, dx: f32, dy: f32) -> bool {
+        use std::f32::consts::{PI, TAU};
+        let off = (dy.atan2(dx) - self.direction.to_radians()).rem_euclid(TAU);
+        off.min(TAU - off) <= self.half_angle.to_radians().min(PI)
+    }
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_flicker_amplitude() -> f32 {
+    FLICKER_AMPLITUDE
+}
+
+impl Light {
+    pub fn new(x: f32, y: f32, r: f32, color: [u8; 3]) -> Self {
+        Self { x, y, r, color, ambient: [0; 3], intensity: 1.0, flicker: false, flicker_amplitude: FLICKER_AMPLITUDE, cone: None }
+    }
+
+    /// Intensity at `time` seconds into the animation.
+    ///
+    /// Steady lights always give `intensity`. Flickering ones wobble around it by up to
+    /// `flicker_amplitude` of it, following a few sines at unrelated frequencies that add up to
+    /// an irregular jitter. `seed` shifts the phase so that several flames don't flicker in step.
+    pub fn intensity_at(&self, time: f32, seed: f32) -> f32 {
+        if !self.flicker {
+            return self.intensity;
+        }
+        let t = time + seed * 17.3;
+        let wobble = 0.5 * (t * 7.3).sin() + 0.3 * (t * 13.1 + 1.7).sin() + 0.2 * (t * 29.7 + 4.1).sin();
+        self.intensity * (1.0 + self.flicker_amplitude * wobble).max(0.0)
+    }
+
+    /// Position of sample `i` out of `n` spread evenly over the disk.
+    ///
+    /// A single sample is the center, i.e. a point light. Up to `BLUE_NOISE_MAX` samples come
+    /// from the `blue_noise` set for `n`, which keeps the penumbra free of the banding a regular
+    /// pattern leaves at low counts; more follow a golden-angle spiral.
+    #[inline]
+    pub fn sample(&self, i: u32, n: u32) -> (f32, f32) {
+        self.sample_pass(i, n, 0)
+    }
+
+    /// `sample` for the `pass`th of a series of sample sets that are averaged together, each one
+    /// different so that together they cover the disk more finely than any one alone. Pass 0 is
+    /// `sample` itself.
+    ///
+    /// Every pass shifts the whole set by another amount along the radius, measured in area so
+    /// that the density stays the same (wrapping round from the rim to the center), and turns it
+    /// by another angle. The shifts and turns follow the additive recurrence of the plastic
+    /// number, so however many passes are taken they stay spread evenly, which random jitter
+    /// wouldn't.
+    #[inline]
+    pub fn sample_pass(&self, i: u32, n: u32, pass: u32) -> (f32, f32) {
+        if n <= 1 {
+            return (self.x, self.y);
+        }
+        let (dx, dy) = if n <= BLUE_NOISE_MAX { blue_noise(n)[i as usize] } else { spiral(i, n) };
+        if pass == 0 {
+            return (self.x + self.r * dx, self.y + self.r * dy);
+        }
+        // 1/p and 1/p² for the plastic number p
+        const R2: (f32, f32) = (0.754_877_7, 0.569_840_3);
+        let k = pass as f32;
+        let r = self.r * (dx * dx + dy * dy + k * R2.0).fract().sqrt();
+        let theta = dy.atan2(dx) + (k * R2.1).fract() * std::f32::consts::TAU;
+        (self.x + r * theta.cos(), self.y + r * theta.sin())
+    }
+
+    /// Whether (x, y) is inside the light's cone, which everywhere is without one.
+    #[inline]
+    pub fn shines_on(&self, x: f32, y: f32) -> bool {
+        self.cone.is_none_or(|cone| cone.admits(x - self.x, y - self.y))
+    }
+
+    /// Return true if (x, y) lies on the light's disk.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        ((x - self.x).powi(2) + (y - self.y).powi(2)).sqrt() <= self.r
+    }
+}
+
+/// `n` points, at most `BLUE_NOISE_MAX`, spread over the unit disk like blue noise: evenly, with
+/// no two close together, but without the rows and rings of a regular pattern.
+///
+/// Each set is made the first time it's asked for and then kept. Mitchell's best-candidate
+/// algorithm places the points one at a time, each the one farthest from the others and from the
+/// rim out of a batch of random candidates, and a few rounds of Lloyd relaxation then even out the
+/// spacing and pull the set onto the center. The seed is fixed, so every run gets the same sets.
+pub fn blue_noise(n: u32) -> &'static [(f32, f32)] {
+    type Set = OnceLock<Box<[(f32, f32)]>>;
+    const SETS: usize = BLUE_NOISE_MAX as usize + 1;
+    static CACHE: [Set; SETS] = [const { OnceLock::new() }; SETS];
+    CACHE[n as usize].get_or_init(|| generate_blue_noise(n as usize))
+}
+
+fn generate_blue_noise(n: usize) -> Box<[(f32, f32)]> {
+    /// Candidates tried per point already placed.
+    const CANDIDATES: usize = 10;
+    /// How much closer than to another point a candidate may come to the rim, which would
+    /// otherwise draw the points outwards.
+    const RIM_WEIGHT: f64 = 4.0;
+    const RELAX_ROUNDS: usize = 8;
+    /// The relaxation measures each point's share of the disk on a grid this many cells across
+    /// the radius.
+    const GRID: i32 = 32;
+
+    // xorshift32, uniform in -1..1
+    let mut state: u32 = 0x9e37_79b9;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as f64 / 2_147_483_648.0 - 1.0
+    };
+    let dist2 = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2);
+
+    let mut points: Vec<(f64, f64)> = Vec::with_capacity(n);
+    while points.len() < n {
+        let mut best = ((0.0, 0.0), f64::MIN);
+        for _ in 0..CANDIDATES * points.len().max(1) {
+            let c = loop {
+                let c = (random(), random());
+                if dist2(c, (0.0, 0.0)) <= 1.0 {
+                    break c;
+                }
+            };
+            let rim = (RIM_WEIGHT * (1.0 - dist2(c, (0.0, 0.0)).sqrt())).powi(2);
+            let gap = points.iter().map(|&p| dist2(c, p)).fold(rim, f64::min);
+            if gap > best.1 {
+                best = (c, gap);
+            }
+        }
+        points.push(best.0);
+    }
+
+    let cells: Vec<(f64, f64)> = (-GRID..GRID)
+        .flat_map(|i| (-GRID..GRID).map(move |j| ((i as f64 + 0.5) / GRID as f64, (j as f64 + 0.5) / GRID as f64)))
+        .filter(|&c| dist2(c, (0.0, 0.0)) <= 1.0)
+        .collect();
+    for _ in 0..RELAX_ROUNDS {
+        // Move each point to the middle of the cells nearer to it than to any other
+        let mut sums = vec![(0.0, 0.0, 0u32); n];
+        for &c in &cells {
+            let nearest = (0..n).min_by(|&a, &b| dist2(c, points[a]).total_cmp(&dist2(c, points[b]))).unwrap();
+            let sum = &mut sums[nearest];
+            *sum = (sum.0 + c.0, sum.1 + c.1, sum.2 + 1);
+        }
+        for (p, &(x, y, count)) in points.iter_mut().zip(&sums) {
+            if count > 0 {
+                *p = (x / count as f64, y / count as f64);
+            }
+        }
+    }
+    points.into_iter().map(|(x, y)| (x as f32, y as f32)).collect()
+}
+
+/// Point `i` of `n` on a golden-angle spiral over the unit disk.
+fn spiral(i: u32, n: u32) -> (f32, f32) {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+    let r = ((i as f32 + 0.5) / n as f32).sqrt();
+    let theta = i as f32 * GOLDEN_ANGLE;
+    (r * theta.cos(), r * theta.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cones_admit_only_their_beam() {
+        let mut light = Light::new(100.0, 100.0, 10.0, [255; 3]);
+        assert!(light.shines_on(0.0, 0.0));
+        // Pointing straight down, 30° either side, across the ±180° seam for the second
+        light.cone = Some(Cone { direction: 90.0, half_angle: 30.0 });
+        assert!(light.shines_on(100.0, 200.0) && light.shines_on(130.0, 200.0));
+        assert!(!light.shines_on(200.0, 120.0) && !light.shines_on(100.0, 0.0));
+        light.cone = Some(Cone { direction: -170.0, half_angle: 20.0 });
+        assert!(light.shines_on(0.0, 90.0) && light.shines_on(0.0, 110.0));
+        assert!(!light.shines_on(0.0, 150.0));
+    }
+
+    #[test]
+    fn blue_noise_sets_are_centered_and_evenly_spaced() {
+        for n in [4, 16, 64] {
+            let set = blue_noise(n);
+            assert_eq!(set.len(), n as usize);
+            assert!(set.iter().all(|&(x, y)| x * x + y * y <= 1.0));
+            let (cx, cy) = set.iter().fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+            assert!(cx.hypot(cy) / (n as f32) < 0.05, "{n} samples centered on ({cx}, {cy})");
+            // Evenly packed points would be about 1/sqrt(n) apart; none may come much closer
+            let closest = set.iter().enumerate()
+                .flat_map(|(i, a)| set[i + 1..].iter().map(move |b| (a.0 - b.0).hypot(a.1 - b.1)))
+                .fold(f32::MAX, f32::min);
+            assert!(closest > 0.5 / (n as f32).sqrt(), "{n} samples only {closest} apart");
+        }
+    }
+}
@@ -1,111 +1,156 @@
+use clap::Parser;
 use error_iter::ErrorIter as _;
-use log::error;
-use pixels::{Error, Pixels, SurfaceTexture};
-use rayon::prelude::*;
-use std::time::Instant;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use log::{error, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use pixels::{PixelsBuilder, SurfaceTexture};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::EventLoop;
 use winit::keyboard::KeyCode;
-use winit::window::WindowBuilder;
+use winit::window::{Fullscreen, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 use std::io::{self, Write};
 use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
 use wgpu::Instance;
-use sysinfo::{System, CpuRefreshKind, MemoryRefreshKind, RefreshKind};
-
-const WIDTH: u32 = 1280;
-const HEIGHT: u32 = 720;
-
-const CIRCLE_X: f32 = 850.0;
-const CIRCLE_Y: f32 = 720.0/2.0;
-const CIRCLE_R: f32 = 150.0;
-
-const LIGHT_X: f32 = 200.0;
-const LIGHT_Y: f32 = 720.0/2.0;
-const LIGHT_R: f32 = 25.0;
-
-struct World {
-    dragging: bool,
-    light_x: f32,
-    light_y: f32,
-    circle_y: f32,
-    circle_vy: f32,
-    last_update: Instant,
-}
 
-struct SystemMonitor {
-    sys: System,
-    cpu_name: String,
-}
+use raytracing_rs::bloom::Bloom;
+use raytracing_rs::vignette::Vignette;
+use raytracing_rs::gpu::GpuRenderer;
+use raytracing_rs::record::Recorder;
+use raytracing_rs::scene::{Floor, Scene};
+use raytracing_rs::{export, occluder, overlay, World};
 
-impl SystemMonitor {
-    fn new() -> Self {
-        let sys = System::new_with_specifics(
-            RefreshKind::new()
-                .with_cpu(CpuRefreshKind::everything())
-                .with_memory(MemoryRefreshKind::everything())
-        );
-        let cpu_name = sys.cpus()[0].name().to_string();
-        Self { sys, cpu_name }
-    }
+mod cli;
+mod error;
+mod metrics;
+mod monitor;
 
-    fn update(&mut self) -> (f32, f32, f32) {
-        self.sys.refresh_cpu();
-        self.sys.refresh_memory();
-        
-        let cpu_usage = self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).sum::<f32>() / 
-                       self.sys.cpus().len() as f32;
-        let memory_used = self.sys.used_memory() as f32 / (1024.0 * 1024.0); // Convert to GB
-        let memory_total = self.sys.total_memory() as f32 / (1024.0 * 1024.0);
-        let memory_percent = (memory_used / memory_total) * 100.0;
-        
-        (cpu_usage, memory_used, memory_percent)
-    }
-}
+use cli::{Args, Format, Renderer};
+use error::AppError;
+use metrics::{FrameTimes, MetricsLog, StageTimes};
+use monitor::{core_bars, SystemMonitor};
+
+/// Simulated time between headless frames, as if shown on a 60 Hz display.
+const HEADLESS_DT: f32 = 1.0 / 60.0;
 
-async fn get_gpu_info() {
+/// Smallest the window can be resized to, in logical pixels.
+const MIN_WINDOW_SIZE: LogicalSize<f64> = LogicalSize::new(320.0, 180.0);
+
+/// Name of the adapter wgpu would pick, or `None` on machines without one, such as CI runners
+/// and VMs.
+async fn get_gpu_info() -> Option<String> {
     let instance = Instance::new(wgpu::InstanceDescriptor::default());
     let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
         power_preference: wgpu::PowerPreference::HighPerformance,
         ..Default::default()
-    }).await.unwrap();
-
-    println!("Using GPU: {}", adapter.get_info().name);
+    }).await?;
+    Some(adapter.get_info().name)
 }
 
-fn main() -> Result<(), Error> {
+/// Run the app, logging whatever stopped it early and exiting with a failure status then.
+fn main() -> ExitCode {
     env_logger::init();
+
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            log_error("run", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), AppError> {
+    let mut scene = match &args.scene {
+        Some(path) => Scene::load(path)?,
+        None => Scene::default(),
+    };
+    override_scene(&mut scene, &args);
+    let mut width = args.width.unwrap_or(scene.width);
+    let mut height = args.height.unwrap_or(scene.height);
+    let mut world = World::new(scene);
+    world.resize(width, height);
+    world.set_drag_smoothing(args.drag_smoothing);
+    world.set_snap_size(args.snap_size);
+    world.set_bloom(args.bloom, Bloom { threshold: args.bloom_threshold, radius: args.bloom_radius });
+    world.set_vignette(args.vignette, Vignette { strength: args.vignette_strength, radius: args.vignette_radius });
+    world.set_trails(args.trails, args.trail_decay);
+    world.set_orbit_motion(args.orbit_radius, args.orbit_speed);
+    if args.orbit {
+        world.start_orbit(0);
+    }
+    #[cfg(feature = "image")]
+    if let Some(path) = &args.background {
+        let image = image::open(path).map_err(AppError::Background)?;
+        world.set_background(image.into_rgba8());
+    }
+
+    // Zero threads lets rayon pick one per core
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.threads.unwrap_or(0) as usize).build()?;
+
+    if args.headless {
+        return run_headless(world, &pool, width, height, &args).map_err(AppError::Headless);
+    }
     
     let mut sys_monitor = SystemMonitor::new();
     
-    println!("CPU: {}", sys_monitor.cpu_name);
-    tokio::runtime::Runtime::new()
-        .unwrap()
-        .block_on(get_gpu_info());
+    println!("CPU: {} ({} render threads)", sys_monitor.cpu_name().unwrap_or("unknown"), pool.current_num_threads());
+    // Only the name is wanted here; the pixels setup below asks for an adapter of its own
+    match tokio::runtime::Runtime::new().map_err(AppError::Runtime)?.block_on(get_gpu_info()) {
+        Some(name) => println!("Using GPU: {name}"),
+        None => warn!("no GPU adapter found, continuing"),
+    }
     
     unsafe { env::set_var("WGPU_POWER_PREF", "high") };
-    let event_loop = EventLoop::new().unwrap();
+    let event_loop = EventLoop::new().map_err(AppError::EventLoop)?;
     let mut input = WinitInputHelper::new();
     let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
+        let size = LogicalSize::new(width as f64, height as f64);
         WindowBuilder::new()
-            .with_title("Raytracing ")
-            .with_resizable(false)
+            .with_title(&args.title)
             .with_inner_size(size)
-            .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
+            .with_min_inner_size(MIN_WINDOW_SIZE)
+            .build(&event_loop)?
     };
 
     let mut pixels = {
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture)?
+        PixelsBuilder::new(width, height, surface_texture)
+            .enable_vsync(!args.uncapped)
+            .build()?
     };
-    let mut world = World::new();
+    // G switches renderer at any time; the GPU one is only set up once it's first needed
+    let mut renderer = args.renderer;
+    let mut gpu: Option<GpuRenderer> = None;
     let mut last_time = Instant::now();
+    let frame_time = args.max_fps.map(|fps| Duration::from_secs_f64(1.0 / fps as f64));
+    let mut last_frame = Instant::now();
+    let mut frame_times = FrameTimes::default();
+    let mut stage_times = StageTimes::default();
+    let mut last_update = Instant::now();
     let mut frames = 0;
+    let mut recorder: Option<Recorder> = None;
+    let mut stats = String::new();
+    let mut show_stats = true;
+    let mut show_rays = false;
+    // Where F11 puts the window back to when leaving fullscreen
+    let mut windowed_size = window.inner_size();
+    // Editing the scene file reloads it; a scene that fails to load leaves the last one up
+    let watch = args.scene.as_deref().and_then(watch_scene);
+    let mut metrics = args.metrics_csv.as_deref().and_then(|path| match MetricsLog::open(path) {
+        Ok(log) => Some(log),
+        Err(err) => {
+            log_error("MetricsLog::open", err);
+            None
+        }
+    });
 
     let res = event_loop.run(|event, elwt| {
         // Draw the current frame
@@ -114,20 +159,88 @@ fn main() -> Result<(), Error> {
             ..
         } = event
         {
+            // Sleep off whatever is left of this frame's slot when capped, instead of spinning
+            if let Some(frame_time) = frame_time {
+                std::thread::sleep(frame_time.saturating_sub(last_frame.elapsed()));
+            }
+            let now = Instant::now();
+            frame_times.push(now.duration_since(last_frame).as_secs_f32());
+            last_frame = now;
+
             frames += 1;
             let elapsed = last_time.elapsed().as_secs_f32();
             if elapsed >= 0.1 {
                 let fps = frames as f32 / elapsed;
-                let (cpu_usage, mem_used, mem_percent) = sys_monitor.update();
-                print!("\rFPS: {:.1} | CPU: {:.1}% | RAM: {:.1}GB ({:.1}%)", 
-                    fps, cpu_usage, mem_used, mem_percent);
+                let system = sys_monitor.update();
+                stats = format!("FPS: {fps:.1}");
+                if let Some(low) = frame_times.low(0.01) {
+                    stats += &format!(" (1% low {low:.1}");
+                    if let Some(low) = frame_times.low(0.001) {
+                        stats += &format!(", 0.1% low {low:.1}");
+                    }
+                    stats += ")";
+                }
+                stats += &format!(" | {} renderer", renderer.name().to_uppercase());
+                let missing = world.cpu_only_effects();
+                if renderer == Renderer::Gpu && !missing.is_empty() {
+                    stats += &format!(" (without {})", missing.join(", "));
+                }
+                stats += &format!(" | {}", stage_times.take(frames));
+                if cfg!(feature = "intersection-stats") {
+                    let per_frame = occluder::take_intersection_tests() as f32 / frames as f32;
+                    stats += &format!(" | Tests: {:.2}M/frame", per_frame / 1e6);
+                }
+                if world.time_scale() != 1.0 {
+                    stats += &format!(" | Speed: {}x", world.time_scale());
+                }
+                if let Some(system) = &system {
+                    stats += &format!(" | CPU: {:.1}% | RAM: {:.1}GB ({:.1}%)", system.cpu_usage, system.mem_used, system.mem_percent);
+                }
+                if let Some(log) = &mut metrics
+                    && let Err(err) = log.record(fps, system.as_ref())
+                {
+                    log_error("MetricsLog::record", err);
+                    metrics = None;
+                }
+                if let Some(gpu) = system.as_ref().and_then(|s| s.gpu.as_ref()) {
+                    stats += &format!(" | GPU: {}% {}C", gpu.utilization, gpu.temperature);
+                }
+                match &system {
+                    Some(system) => print!("\r{stats} | Cores [{}]", core_bars(&system.per_core)),
+                    None => print!("\r{stats}"),
+                }
                 io::stdout().flush().unwrap();
                 frames = 0;
                 last_time = Instant::now();
             }
 
-            world.draw(pixels.frame_mut());
-            if let Err(err) = pixels.render() {
+            // The GPU renderer only needs the frame for the overlay, unless it's being recorded.
+            // While paused and left alone the CPU frame keeps refining its soft shadows
+            let draw_start = Instant::now();
+            if renderer == Renderer::Cpu || recorder.is_some() {
+                pool.install(|| world.draw_progressive(pixels.frame_mut()));
+            } else {
+                pixels.frame_mut().fill(0);
+            }
+            stage_times.draw += draw_start.elapsed();
+            world.draw_snap_grid(pixels.frame_mut());
+            if show_rays {
+                world.draw_rays(pixels.frame_mut());
+            }
+            if show_stats {
+                overlay::draw_text(pixels.frame_mut(), width, 8, 8, &stats, 2);
+            }
+            world.draw_cursor_readout(pixels.frame_mut());
+            if let Some(recorder) = &mut recorder {
+                recorder.capture(pixels.frame());
+            }
+            let present_start = Instant::now();
+            let rendered = match renderer {
+                Renderer::Gpu => gpu.get_or_insert_with(|| GpuRenderer::new(&pixels)).render(&world, &pixels),
+                Renderer::Cpu => pixels.render(),
+            };
+            stage_times.present += present_start.elapsed();
+            if let Err(err) = rendered {
                 log_error("pixels.render", err);
                 elwt.exit();
                 return;
@@ -142,128 +255,235 @@ fn main() -> Result<(), Error> {
                 return;
             }
 
-            // Resize the window
-            if let Some(size) = input.window_resized() {
+            if input.key_pressed(KeyCode::F1) {
+                show_stats = !show_stats;
+            }
+
+            if input.key_pressed(KeyCode::F2) {
+                show_rays = !show_rays;
+            }
+
+            // Swap renderers to compare them. They draw the same frame except for the effects only
+            // the CPU has, which the stats line lists while the GPU is missing them
+            if input.key_pressed(KeyCode::KeyG) {
+                renderer = match renderer {
+                    Renderer::Gpu => Renderer::Cpu,
+                    Renderer::Cpu => Renderer::Gpu,
+                };
+                let missing = world.cpu_only_effects();
+                if renderer == Renderer::Gpu && !missing.is_empty() {
+                    warn!("The GPU renderer has no {}, so its frame won't match the CPU's", missing.join(", "));
+                }
+            }
+
+            // Toggle borderless fullscreen on the window's monitor; the resize below follows it
+            if input.key_pressed(KeyCode::F11) {
+                if window.fullscreen().is_some() {
+                    window.set_fullscreen(None);
+                    let _ = window.request_inner_size(windowed_size);
+                } else if let Some(monitor) = window.current_monitor() {
+                    windowed_size = window.inner_size();
+                    window.set_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
+                } else {
+                    warn!("Could not query the current monitor, staying windowed");
+                }
+            }
+
+            // Save a screenshot of the last rendered frame
+            if input.key_pressed(KeyCode::KeyP) {
+                if renderer == Renderer::Gpu {
+                    pool.install(|| world.draw(pixels.frame_mut()));
+                    world.draw_snap_grid(pixels.frame_mut());
+                    if show_rays {
+                        world.draw_rays(pixels.frame_mut());
+                    }
+                    if show_stats {
+                        overlay::draw_text(pixels.frame_mut(), width, 8, 8, &stats, 2);
+                    }
+                    world.draw_cursor_readout(pixels.frame_mut());
+                }
+                let format = Format::default();
+                let path = export::timestamped_path("screenshot", format.extension());
+                match format.save(pixels.frame(), width, height, &path) {
+                    Ok(()) => println!("\nSaved {}", path.display()),
+                    Err(err) => log_error("Format::save", err),
+                }
+                match export::save_sidecar(&path, &world, renderer.name()) {
+                    Ok(sidecar) => println!("Saved {}", sidecar.display()),
+                    Err(err) => log_error("save_sidecar", err),
+                }
+            }
+
+            // Save the current scene so it can be loaded again later
+            if input.key_pressed(KeyCode::KeyS) {
+                let path = export::timestamped_path("scene", "json");
+                match world.to_scene().save(&path) {
+                    Ok(()) => println!("\nSaved {}", path.display()),
+                    Err(err) => log_error("Scene::save", err),
+                }
+            }
+
+            // Start or stop recording; encoding runs on its own thread so the window stays responsive
+            if input.key_pressed(KeyCode::KeyR) {
+                match recorder.take() {
+                    Some(rec) => finish_recording(rec),
+                    None => recorder = Some(Recorder::new(width, height)),
+                }
+            }
+
+            // Follow the window's size with the frame too, one pixel per logical pixel as at
+            // startup, so the scene is rendered at the new size rather than stretched. Minimized
+            // windows report a zero size and are left alone
+            if let Some(size) = input.window_resized()
+                && size.width > 0
+                && size.height > 0
+            {
                 if let Err(err) = pixels.resize_surface(size.width, size.height) {
                     log_error("pixels.resize_surface", err);
                     elwt.exit();
                     return;
                 }
+                let logical = size.to_logical::<f64>(window.scale_factor());
+                let (w, h) = ((logical.width.round() as u32).max(1), (logical.height.round() as u32).max(1));
+                if (w, h) != (width, height) {
+                    // The recording's frames all have to be the same size, so it ends here
+                    if let Some(rec) = recorder.take() {
+                        finish_recording(rec);
+                    }
+                    if let Err(err) = pixels.resize_buffer(w, h) {
+                        log_error("pixels.resize_buffer", err);
+                        elwt.exit();
+                        return;
+                    }
+                    (width, height) = (w, h);
+                    world.resize(width, height);
+                }
+            }
+
+            if let (Some((_, rx)), Some(path)) = (&watch, &args.scene)
+                && scene_changed(rx, path)
+            {
+                match Scene::load(path) {
+                    Ok(mut scene) => {
+                        override_scene(&mut scene, &args);
+                        world.load_scene(scene);
+                        println!("\nReloaded {}", path.display());
+                    }
+                    Err(err) => log_error("Scene::load", err),
+                }
             }
 
             // Update internal state and request a redraw
-            world.update(&input);
+            let now = Instant::now();
+            world.update(&input, now.duration_since(last_update).as_secs_f32());
+            stage_times.update += now.elapsed();
+            last_update = now;
             window.request_redraw();
         }
     });
-    res.map_err(|e| Error::UserDefined(Box::new(e)))
+    res.map_err(AppError::Run)
 }
 
-fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
-    error!("{method_name}() failed: {err}");
-    for source in err.sources().skip(1) {
-        error!("  Caused by: {source}");
+/// Apply the command line's changes to a scene, whether just loaded or reloaded.
+fn override_scene(scene: &mut Scene, args: &Args) {
+    scene.max_bounces = args.max_bounces.unwrap_or(scene.max_bounces);
+    scene.wrap |= args.wrap;
+    if let Some(y) = args.floor {
+        scene.floor = Some(Floor { y, reflection: args.floor_reflection });
+    }
+    if let Some((cols, rows)) = args.circles {
+        scene.arrange_circles(cols, rows, args.circle_spacing);
     }
 }
 
-impl World {
-    fn new() -> Self {
-        Self {
-            dragging: false,
-            light_x: LIGHT_X,
-            light_y: LIGHT_Y,
-            circle_y: CIRCLE_Y,
-            circle_vy: 100.0, // Speed in pixels per second
-            last_update: Instant::now(),
+/// Watch the scene file at `path` for changes, or log why it can't be and go without. The
+/// directory is watched rather than the file, since editors often save by replacing the file,
+/// which would end a watch on the old one.
+fn watch_scene(path: &Path) -> Option<(RecommendedWatcher, Receiver<notify::Result<notify::Event>>)> {
+    let (tx, rx) = mpsc::channel();
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let watcher = notify::recommended_watcher(tx).and_then(|mut watcher| {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    match watcher {
+        Ok(watcher) => Some((watcher, rx)),
+        Err(err) => {
+            log_error("watch_scene", err);
+            None
         }
     }
+}
 
-    fn update(&mut self, input: &WinitInputHelper) {
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update).as_secs_f32();
-        self.last_update = now;
-
-        // Check for mouse press inside the light circle
-        if input.mouse_pressed(0) {
-            if let Some((mx, my)) = input.cursor() {
-                let dx = mx as f32 - self.light_x;
-                let dy = my as f32 - self.light_y;
-                if (dx * dx + dy * dy).sqrt() <= LIGHT_R {
-                    self.dragging = true;
-                }
+/// Whether any of the events `rx` has queued up since the last call changed the file at `path`.
+fn scene_changed(rx: &Receiver<notify::Result<notify::Event>>, path: &Path) -> bool {
+    let mut changed = false;
+    for event in rx.try_iter() {
+        match event {
+            Ok(event) => {
+                changed |= (event.kind.is_create() || event.kind.is_modify())
+                    && event.paths.iter().any(|p| p.file_name() == path.file_name());
             }
+            Err(err) => log_error("notify", err),
         }
+    }
+    changed
+}
 
-        // While dragging, follow the mouse
-        if self.dragging && input.mouse_held(0) {
-            if let Some((mx, my)) = input.cursor() {
-                self.light_x = mx as f32;
-                self.light_y = my as f32;
-            }
+/// Encode `rec` to a timestamped GIF on another thread, so the window doesn't stall meanwhile.
+fn finish_recording(rec: Recorder) {
+    let path = export::timestamped_path("recording", "gif");
+    println!("\nWriting {} frames to {}", rec.frame_count(), path.display());
+    std::thread::spawn(move || {
+        if let Err(err) = rec.finish(&path) {
+            log_error("Recorder::finish", err);
         }
+    });
+}
 
-        // Stop dragging when released
-        if input.mouse_released(0) {
-            self.dragging = false;
+/// Simulate and render `args.frames` frames of `width`×`height` on the CPU with `pool`, writing
+/// each to `frame-NNNN.png` in `args.out`, or `.ppm` in that `args.format`. With a sweep instead,
+/// render a still frame at each of its values without any physics, named by the value, e.g.
+/// `light-x-250.png`. Progress bars on stderr follow the run and the current frame's tiles.
+fn run_headless(mut world: World, pool: &rayon::ThreadPool, width: u32, height: u32, args: &Args) -> Result<(), export::Error> {
+    let out = &args.out;
+    fs::create_dir_all(out)?;
+    let names: Vec<(String, Option<f32>)> = match &args.sweep {
+        Some(sweep) => sweep.values().map(|v| (format!("{}-{v}", sweep.param.name()), Some(v))).collect(),
+        None => (0..args.frames).map(|i| (format!("frame-{i:04}"), None)).collect(),
+    };
+    let bars = MultiProgress::new();
+    let run = bars.add(ProgressBar::new(names.len() as u64).with_style(progress_style("frames")));
+    let tiles = bars.add(ProgressBar::new(world.tile_count() as u64).with_style(progress_style("tiles")));
+    let mut frame = vec![0; width as usize * height as usize * 4];
+    for (name, value) in &names {
+        if let (Some(sweep), Some(value)) = (&args.sweep, value) {
+            sweep.apply(&mut world, *value);
         }
-
-        // Move the circle up and down
-        self.circle_y += self.circle_vy * elapsed;
-
-        // Bounce off top/bottom
-        if self.circle_y < CIRCLE_R || self.circle_y > (HEIGHT as f32 - CIRCLE_R) {
-            self.circle_vy = -self.circle_vy;
+        tiles.reset();
+        pool.install(|| world.draw_next_with_progress(&mut frame, || tiles.inc(1)));
+        args.format.save(&frame, width, height, &out.join(format!("{name}.{}", args.format.extension())))?;
+        if args.sweep.is_none() {
+            world.step(HEADLESS_DT);
         }
+        run.inc(1);
     }
-
-    fn draw(&self, frame: &mut [u8]) {
-        frame.par_chunks_exact_mut(4)
-             .enumerate()
-             .for_each(|(i, pixel)| {
-                 let xi = (i % WIDTH as usize) as f32;
-                 let yi = (i / WIDTH as usize) as f32;
-
-                 let dist_light = ((xi - self.light_x).powi(2) + (yi - self.light_y).powi(2)).sqrt();
-                 let dist_circle = ((xi - CIRCLE_X).powi(2) + (yi - self.circle_y).powi(2)).sqrt();
-
-                 // If inside the light circle => white
-                 let rgba = if dist_light <= LIGHT_R {
-                     [0xff, 0xff, 0xff, 0xff]
-                 // Else if inside main circle => white
-                 } else if dist_circle <= CIRCLE_R {
-                     [0xff, 0xff, 0xff, 0xff]
-                 // Else check if in shadow => black, else => yellow
-                 } else if is_shadowed(self.light_x, self.light_y, xi, yi, CIRCLE_X, self.circle_y, CIRCLE_R) {
-                     [0x00, 0x00, 0x00, 0xff]
-                 } else {
-                     [0xff, 0xff, 0x00, 0xff]
-                 };
-
-                 pixel.copy_from_slice(&rgba);
-             });
-    }
+    tiles.finish_and_clear();
+    run.finish();
+    println!("Wrote {} frames to {}", names.len(), out.display());
+    Ok(())
 }
 
-/// Return true if the line from (lx, ly) to (px, py) intersects the circle at (cx, cy) with radius r.
-fn is_shadowed(lx: f32, ly: f32, px: f32, py: f32, cx: f32, cy: f32, r: f32) -> bool {
-    let dx = px - lx;
-    let dy = py - ly;
-    let fx = lx - cx;
-    let fy = ly - cy;
-
-    let a = dx*dx + dy*dy;
-    let b = 2.0 * (fx*dx + fy*dy);
-    let c = fx*fx + fy*fy - r*r;
+/// A bar counting `unit`, with how long is left at the current rate.
+fn progress_style(unit: &str) -> ProgressStyle {
+    ProgressStyle::with_template(&format!("{{bar:40}} {{pos:>5}}/{{len}} {unit} ({{eta}} left)"))
+        .expect("progress template should parse")
+}
 
-    let disc = b*b - 4.0*a*c;
-    if disc < 0.0 {
-        return false; // no intersection
+fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
+    error!("{method_name}() failed: {err}");
+    for source in err.sources().skip(1) {
+        error!("  Caused by: {source}");
     }
+}
 
-    let disc_sqrt = disc.sqrt();
-    let t1 = (-b - disc_sqrt) / (2.0*a);
-    let t2 = (-b + disc_sqrt) / (2.0*a);
-
-    // If either t is between 0 and 1, we have an intersection before reaching (px, py).
-    (0.0..=1.0).contains(&t1) || (0.0..=1.0).contains(&t2)
-}
\ No newline at end of file
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// Specular exponent of materials that don't set their own.
+pub const DEFAULT_SHININESS: f32 = 32.0;
+
+/// How a surface responds to light. The default is the plain white surface every shape used to
+/// have.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    /// Share of each channel of incoming light that is diffusely reflected.
+    pub albedo: [u8; 3],
+    /// Light given off by the surface itself, whether or not any light reaches it.
+    pub emissive: [u8; 3],
+    /// How mirror-like the surface is, from 0 (matte) to 1 (a perfect mirror).
+    pub reflectivity: f32,
+    /// Specular exponent: higher values give a smaller, sharper highlight, and zero turns it off.
+    pub shininess: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self { albedo: [0xff; 3], emissive: [0; 3], reflectivity: 0.0, shininess: DEFAULT_SHININESS }
+    }
+}
+
+impl Material {
+    /// A surface that only glows in `color`, like a light's disk.
+    pub fn emitting(color: [u8; 3]) -> Self {
+        Self { albedo: [0; 3], emissive: color, reflectivity: 0.0, shininess: 0.0 }
+    }
+}
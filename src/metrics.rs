@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::monitor::SystemStats;
+
+/// Rows written between flushes, so a crash loses at most a second or so of stats.
+const FLUSH_EVERY: u32 = 10;
+
+/// Frames kept for the percentile lows: at 144 FPS, the last 14 seconds or so. The 0.1% low
+/// needs a thousand frames before it means anything.
+pub const FRAME_WINDOW: usize = 2000;
+
+/// Rolling window of the last `FRAME_WINDOW` frame times, in seconds.
+#[derive(Default)]
+pub struct FrameTimes {
+    times: VecDeque<f32>,
+}
+
+impl FrameTimes {
+    pub fn push(&mut self, dt: f32) {
+        if self.times.len() == FRAME_WINDOW {
+            self.times.pop_front();
+        }
+        self.times.push_back(dt);
+    }
+
+    /// The FPS that all but the slowest `fraction` of frames beat, e.g. 0.01 for the 1% low: one
+    /// over the frame time at that percentile. `None` until there are enough frames to tell.
+    pub fn low(&self, fraction: f32) -> Option<f32> {
+        let slowest = (self.times.len() as f32 * fraction).floor() as usize;
+        if slowest == 0 {
+            return None;
+        }
+        let mut sorted: Vec<f32> = self.times.iter().copied().collect();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+        Some(1.0 / sorted[slowest - 1])
+    }
+}
+
+/// Time spent in each stage of the frames since the last `take`: stepping the world, drawing
+/// the frame, and handing it to the window.
+#[derive(Default)]
+pub struct StageTimes {
+    pub update: Duration,
+    pub draw: Duration,
+    pub present: Duration,
+}
+
+impl StageTimes {
+    /// Each stage's average over `frames` frames, written like `update 0.1ms | draw 4.2ms |
+    /// present 1.1ms`, starting every sum over.
+    pub fn take(&mut self, frames: u32) -> String {
+        let Self { update, draw, present } = std::mem::take(self);
+        let ms = |d: Duration| d.as_secs_f32() * 1000.0 / frames.max(1) as f32;
+        format!("update {:.1}ms | draw {:.1}ms | present {:.1}ms", ms(update), ms(draw), ms(present))
+    }
+}
+
+/// Appends one CSV row of performance stats per stats update.
+pub struct MetricsLog {
+    out: BufWriter<File>,
+    unflushed: u32,
+}
+
+impl MetricsLog {
+    /// Open `path` for appending, writing the header first if the file is new or empty.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut out = BufWriter::new(file);
+        if empty {
+            writeln!(out, "timestamp,fps,cpu_percent,mem_used_gb,mem_percent")?;
+        }
+        Ok(Self { out, unflushed: 0 })
+    }
+
+    /// Add a row stamped with the current time, in seconds since the Unix epoch. Without a
+    /// `system` reading the CPU and memory columns are left empty.
+    pub fn record(&mut self, fps: f32, system: Option<&SystemStats>) -> io::Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        match system {
+            Some(s) => writeln!(self.out, "{timestamp:.3},{fps:.1},{:.1},{:.2},{:.1}", s.cpu_usage, s.mem_used, s.mem_percent)?,
+            None => writeln!(self.out, "{timestamp:.3},{fps:.1},,,")?,
+        }
+        self.unflushed += 1;
+        if self.unflushed >= FLUSH_EVERY {
+            self.unflushed = 0;
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lows_come_from_the_slowest_frames() {
+        let mut times = FrameTimes::default();
+        for i in 0..1000 {
+            // Ten 50 ms hitches among 10 ms frames
+            times.push(if i % 100 == 0 { 0.05 } else { 0.01 });
+        }
+        assert_eq!(times.low(0.01), Some(20.0));
+        assert_eq!(times.low(0.1).map(f32::round), Some(100.0));
+        assert_eq!(times.low(0.0001), None);
+
+        for _ in 0..FRAME_WINDOW {
+            times.push(0.01);
+        }
+        assert_eq!(times.low(0.01).map(f32::round), Some(100.0));
+    }
+
+    #[test]
+    fn stage_times_average_and_start_over() {
+        let mut stages = StageTimes::default();
+        for _ in 0..4 {
+            stages.update += Duration::from_micros(100);
+            stages.draw += Duration::from_millis(4);
+            stages.present += Duration::from_micros(1250);
+        }
+        assert_eq!(stages.take(4), "update 0.1ms | draw 4.0ms | present 1.2ms");
+        assert_eq!(stages.take(0), "update 0.0ms | draw 0.0ms | present 0.0ms");
+    }
+}
@@ -0,0 +1,108 @@
+//! CPU, memory and GPU readings for the stats line. Without the `monitor` feature there are none,
+//! and the stats line shows only the frame rate.
+
+#[cfg(feature = "monitor")]
+use log::info;
+#[cfg(feature = "monitor")]
+use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+#[cfg(feature = "monitor")]
+use nvml_wrapper::Nvml;
+#[cfg(feature = "monitor")]
+use sysinfo::{System, CpuRefreshKind, MemoryRefreshKind, RefreshKind};
+
+/// One reading of the machine's load.
+pub struct SystemStats {
+    /// Average over all cores, in percent.
+    pub cpu_usage: f32,
+    pub mem_used: f32,
+    pub mem_percent: f32,
+    /// Each core's usage in percent.
+    pub per_core: Vec<f32>,
+    pub gpu: Option<GpuStats>,
+}
+
+/// Live readings from the first NVIDIA GPU.
+pub struct GpuStats {
+    pub utilization: u32,
+    pub temperature: u32,
+}
+
+#[cfg(feature = "monitor")]
+pub struct SystemMonitor {
+    sys: System,
+    cpu_name: String,
+    /// Only there on machines with an NVIDIA driver; elsewhere the GPU stats are left out.
+    nvml: Option<Nvml>,
+}
+
+#[cfg(feature = "monitor")]
+impl SystemMonitor {
+    pub fn new() -> Self {
+        let sys = System::new_with_specifics(
+            RefreshKind::new()
+                .with_cpu(CpuRefreshKind::everything())
+                .with_memory(MemoryRefreshKind::everything())
+        );
+        let cpu_name = sys.cpus()[0].name().to_string();
+        let nvml = Nvml::init().inspect_err(|err| info!("No NVML, GPU stats disabled: {err}")).ok();
+        Self { sys, cpu_name, nvml }
+    }
+
+    pub fn cpu_name(&self) -> Option<&str> {
+        Some(&self.cpu_name)
+    }
+
+    pub fn update(&mut self) -> Option<SystemStats> {
+        self.sys.refresh_cpu();
+        self.sys.refresh_memory();
+
+        let cpu_usage = self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).sum::<f32>() /
+                       self.sys.cpus().len() as f32;
+        let memory_used = self.sys.used_memory() as f32 / (1024.0 * 1024.0); // Convert to GB
+        let memory_total = self.sys.total_memory() as f32 / (1024.0 * 1024.0);
+        let memory_percent = (memory_used / memory_total) * 100.0;
+
+        Some(SystemStats {
+            cpu_usage,
+            mem_used: memory_used,
+            mem_percent: memory_percent,
+            per_core: self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            gpu: self.gpu_stats(),
+        })
+    }
+
+    fn gpu_stats(&self) -> Option<GpuStats> {
+        let device = self.nvml.as_ref()?.device_by_index(0).ok()?;
+        Some(GpuStats {
+            utilization: device.utilization_rates().ok()?.gpu,
+            temperature: device.temperature(TemperatureSensor::Gpu).ok()?,
+        })
+    }
+}
+
+/// Stand-in for builds without the `monitor` feature, which never has a reading.
+#[cfg(not(feature = "monitor"))]
+pub struct SystemMonitor;
+
+#[cfg(not(feature = "monitor"))]
+impl SystemMonitor {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn cpu_name(&self) -> Option<&str> {
+        None
+    }
+
+    pub fn update(&mut self) -> Option<SystemStats> {
+        None
+    }
+}
+
+/// One block character per core, from empty at 0% to full at 100%.
+pub fn core_bars(usages: &[f32]) -> String {
+    const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    usages.iter()
+        .map(|u| BARS[((u / 100.0 * 8.0).round() as usize).min(8)])
+        .collect()
+}
@@ -0,0 +1,785 @@
+#[cfg(feature = "intersection-stats")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use wide::{f32x8, CmpGe, CmpGt, CmpLe, CmpLt};
+
+use crate::material::Material;
+
+/// Ray-shape tests run since the last `take_intersection_tests`. Every test across every
+/// thread bumps this one counter, which is why it's only there with the `intersection-stats`
+/// feature.
+#[cfg(feature = "intersection-stats")]
+static INTERSECTION_TESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Count `n` ray-shape tests, if they're being counted.
+#[inline]
+fn count_tests(n: u64) {
+    #[cfg(feature = "intersection-stats")]
+    INTERSECTION_TESTS.fetch_add(n, Ordering::Relaxed);
+    #[cfg(not(feature = "intersection-stats"))]
+    let _ = n;
+}
+
+/// Ray-shape tests run since the last call, starting the count over: one per shape tested
+/// against a shadow ray, so each lane of `is_shadowed_x8` counts, while a polygon counts once
+/// however many edges it has. Always zero without the `intersection-stats` feature.
+pub fn take_intersection_tests() -> u64 {
+    #[cfg(feature = "intersection-stats")]
+    return INTERSECTION_TESTS.swap(0, Ordering::Relaxed);
+    #[cfg(not(feature = "intersection-stats"))]
+    0
+}
+
+/// Pixels handled at once by `is_shadowed_x8`.
+pub const LANES: usize = 8;
+
+/// A shape that fills the pixels it covers and casts a shadow away from the light.
+pub trait Occluder: Send + Sync {
+    /// Return true if the segment from the light (lx, ly) to the pixel (px, py) is blocked by the shape.
+    ///
+    /// A light inside the shape, or on its edge, shines out through it: the shape casts no shadow
+    /// from that light rather than shadowing the whole frame.
+    fn blocks_ray(&self, lx: f32, ly: f32, px: f32, py: f32) -> bool;
+
+    /// Return true if (x, y) lies inside the shape.
+    fn contains(&self, x: f32, y: f32) -> bool;
+
+    /// Axis-aligned box around the shape, as `[min x, min y, max x, max y]`.
+    fn bounds(&self) -> [f32; 4];
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Circle {
+    pub x: f32,
+    pub y: f32,
+    pub r: f32,
+    /// Horizontal speed in pixels per second. Zero, the default, for circles that only bounce up
+    /// and down until something knocks them sideways.
+    #[serde(default)]
+    pub vx: f32,
+    /// Vertical speed in pixels per second.
+    pub vy: f32,
+    #[serde(default)]
+    pub material: Material,
+    /// Share of the light crossing the circle that it stops, from 0 (invisible) to 1, the
+    /// default, for a hard shadow. In between, the circle casts a partial shadow and the
+    /// background shows through it.
+    #[serde(default = "opaque")]
+    pub opacity: f32,
+}
+
+fn opaque() -> f32 {
+    1.0
+}
+
+impl Circle {
+    pub fn new(x: f32, y: f32, r: f32, vy: f32) -> Self {
+        Self { x, y, r, vx: 0.0, vy, material: Material::default(), opacity: opaque() }
+    }
+
+    /// `blocks_ray` for eight pixels at once, see `is_shadowed_x8`.
+    #[inline]
+    pub fn blocks_rays_x8(&self, lx: f32, ly: f32, px: f32x8, py: f32x8) -> u32 {
+        is_shadowed_x8(lx, ly, px, py, self.x, self.y, self.r)
+    }
+}
+
+impl Occluder for Circle {
+    fn blocks_ray(&self, lx: f32, ly: f32, px: f32, py: f32) -> bool {
+        is_shadowed(lx, ly, px, py, self.x, self.y, self.r)
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        ((x - self.x).powi(2) + (y - self.y).powi(2)).sqrt() <= self.r
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.x - self.r, self.y - self.r, self.x + self.r, self.y + self.r]
+    }
+}
+
+/// Axis-aligned rectangle with its top-left corner at (x, y).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
+impl Occluder for Rect {
+    fn blocks_ray(&self, lx: f32, ly: f32, px: f32, py: f32) -> bool {
+        count_tests(1);
+        !self.contains(lx, ly) && segment_hits_rect(lx, ly, px, py, self.x, self.y, self.w, self.h)
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        (self.x..=self.x + self.w).contains(&x) && (self.y..=self.y + self.h).contains(&y)
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.x, self.y, self.x + self.w, self.y + self.h]
+    }
+}
+
+/// Infinitely thin wall between two points. It casts a shadow but has no interior to fill.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub a: (f32, f32),
+    pub b: (f32, f32),
+}
+
+impl Segment {
+    pub fn new(a: (f32, f32), b: (f32, f32)) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Occluder for Segment {
+    fn blocks_ray(&self, lx: f32, ly: f32, px: f32, py: f32) -> bool {
+        count_tests(1);
+        segment_blocks(lx, ly, px, py, self)
+    }
+
+    fn contains(&self, _x: f32, _y: f32) -> bool {
+        false
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.a.0.min(self.b.0), self.a.1.min(self.b.1), self.a.0.max(self.b.0), self.a.1.max(self.b.1)]
+    }
+}
+
+/// Convex polygon given by its vertices in either winding order.
+///
+/// Polygons with fewer than three vertices have no area; they neither fill nor block.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Polygon {
+    pub verts: Vec<(f32, f32)>,
+}
+
+impl Polygon {
+    pub fn new(verts: Vec<(f32, f32)>) -> Self {
+        Self { verts }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = Segment> + '_ {
+        let n = self.verts.len();
+        (0..n).map(move |i| Segment::new(self.verts[i], self.verts[(i + 1) % n]))
+    }
+}
+
+impl Occluder for Polygon {
+    fn blocks_ray(&self, lx: f32, ly: f32, px: f32, py: f32) -> bool {
+        count_tests(1);
+        self.verts.len() >= 3 && !self.contains(lx, ly) && self.edges().any(|edge| segment_blocks(lx, ly, px, py, &edge))
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        if self.verts.len() < 3 {
+            return false;
+        }
+
+        // Inside a convex polygon the point sits on the same side of every edge,
+        // whichever way the vertices wind.
+        let mut sign = 0.0f32;
+        for edge in self.edges() {
+            let side = cross(edge.b.0 - edge.a.0, edge.b.1 - edge.a.1, x - edge.a.0, y - edge.a.1);
+            if side == 0.0 {
+                continue;
+            }
+            if sign == 0.0 {
+                sign = side.signum();
+            } else if side.signum() != sign {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Empty (min above max) for polygons with no vertices.
+    fn bounds(&self) -> [f32; 4] {
+        self.verts.iter().fold(
+            [f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY],
+            |[x0, y0, x1, y1], &(x, y)| [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+        )
+    }
+}
+
+/// Most sphere-marching steps an `Sdf` takes along a shadow ray unless told otherwise, and how
+/// close to its edge a step has to land to count as a hit, in scene pixels.
+pub const SDF_STEPS: u32 = 64;
+pub const SDF_EPSILON: f32 = 0.1;
+
+/// One piece of an `Sdf`, written with a `"type"` of `"circle"` or `"rounded_rect"`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SdfShape {
+    Circle { x: f32, y: f32, r: f32 },
+    /// `w`×`h` box centered on (x, y), its corners rounded off by `radius`, up to half the
+    /// shorter side.
+    RoundedRect { x: f32, y: f32, w: f32, h: f32, radius: f32 },
+}
+
+impl SdfShape {
+    /// Signed distance from (x, y) to the shape's edge, negative inside.
+    pub fn distance(&self, x: f32, y: f32) -> f32 {
+        match *self {
+            SdfShape::Circle { x: cx, y: cy, r } => (x - cx).hypot(y - cy) - r,
+            SdfShape::RoundedRect { x: cx, y: cy, w, h, radius } => {
+                let radius = radius.clamp(0.0, w.min(h) / 2.0);
+                let qx = (x - cx).abs() - (w / 2.0 - radius);
+                let qy = (y - cy).abs() - (h / 2.0 - radius);
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
+            }
+        }
+    }
+
+    /// As `Occluder::bounds`.
+    pub fn bounds(&self) -> [f32; 4] {
+        match *self {
+            SdfShape::Circle { x, y, r } => [x - r, y - r, x + r, y + r],
+            SdfShape::RoundedRect { x, y, w, h, .. } => [x - w / 2.0, y - h / 2.0, x + w / 2.0, y + h / 2.0],
+        }
+    }
+}
+
+/// Shape given by a signed distance function instead of its outline: the union of `shapes`.
+///
+/// A shadow ray is sphere-marched from the light toward the pixel, each step as long as the
+/// distance to the nearest edge so it can't step over the shape. It's blocked once a step
+/// lands within `epsilon` of the edge, and lit once it reaches the pixel. A ray still short of
+/// the pixel after `steps` steps is crawling into a crease between shapes and counts as blocked,
+/// so too few steps grows the shadow a little rather than letting light through the crease.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sdf {
+    pub shapes: Vec<SdfShape>,
+    #[serde(default = "sdf_steps")]
+    pub steps: u32,
+    #[serde(default = "sdf_epsilon")]
+    pub epsilon: f32,
+}
+
+fn sdf_steps() -> u32 {
+    SDF_STEPS
+}
+
+fn sdf_epsilon() -> f32 {
+    SDF_EPSILON
+}
+
+impl Sdf {
+    pub fn new(shapes: Vec<SdfShape>) -> Self {
+        Self { shapes, steps: SDF_STEPS, epsilon: SDF_EPSILON }
+    }
+
+    /// Signed distance from (x, y) to the nearest shape, or infinity without any.
+    pub fn distance(&self, x: f32, y: f32) -> f32 {
+        self.shapes.iter().map(|s| s.distance(x, y)).fold(f32::INFINITY, f32::min)
+    }
+}
+
+impl Occluder for Sdf {
+    fn blocks_ray(&self, lx: f32, ly: f32, px: f32, py: f32) -> bool {
+        count_tests(1);
+        if self.contains(lx, ly) {
+            return false;
+        }
+        let len = (px - lx).hypot(py - ly);
+        let (dx, dy) = ((px - lx) / len, (py - ly) / len);
+        let mut t = 0.0;
+        for _ in 0..self.steps {
+            if t >= len {
+                return false;
+            }
+            let d = self.distance(lx + dx * t, ly + dy * t);
+            if d < self.epsilon {
+                return true;
+            }
+            t += d;
+        }
+        t < len
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.distance(x, y) <= 0.0
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        self.shapes.iter().map(SdfShape::bounds).fold(
+            [f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY],
+            |[x0, y0, x1, y1], [a, b, c, d]| [x0.min(a), y0.min(b), x1.max(c), y1.max(d)],
+        )
+    }
+}
+
+/// Return true if the line from (lx, ly) to (px, py) intersects the circle at (cx, cy) with radius r.
+///
+/// Only the segment between the two points counts, ends included, and the circle is taken as
+/// filled with its edge included. Wherever the three points are:
+/// - a light inside the circle or on its edge is never shadowed by it, see `Occluder::blocks_ray`
+/// - a pixel on the light's side that stops short of the circle, or anywhere behind the light,
+///   is lit, even when the line through both would go on to cross the circle
+/// - a pixel whose segment passes through or grazes the circle is shadowed, and so is a pixel
+///   inside it
+/// - a pixel at the light itself is lit
+pub fn is_shadowed(lx: f32, ly: f32, px: f32, py: f32, cx: f32, cy: f32, r: f32) -> bool {
+    count_tests(1);
+    let dx = px - lx;
+    let dy = py - ly;
+    let fx = lx - cx;
+    let fy = ly - cy;
+
+    let c = fx*fx + fy*fy - r*r;
+    if c <= 0.0 {
+        return false; // light inside the circle
+    }
+    let a = dx*dx + dy*dy;
+    let b = 2.0 * (fx*dx + fy*dy);
+
+    let disc = b*b - 4.0*a*c;
+    if disc < 0.0 {
+        return false; // no intersection
+    }
+    if can_skip_roots(a, b, c) {
+        return false;
+    }
+
+    let disc_sqrt = disc.sqrt();
+    let t1 = (-b - disc_sqrt) / (2.0*a);
+    let t2 = (-b + disc_sqrt) / (2.0*a);
+
+    // If either t is between 0 and 1, we have an intersection before reaching (px, py).
+    (0.0..=1.0).contains(&t1) || (0.0..=1.0).contains(&t2)
+}
+
+/// Cheap test on the coefficients of `is_shadowed`'s quadratic that rules out a hit without
+/// solving for the roots.
+///
+/// With the light outside the circle (`c > 0`, already checked) both roots share a sign, so the
+/// segment can't reach the circle when the pixel lies on the far side of the light from it
+/// (`b >= 0`, both roots negative), or when it stops short of the circle's near edge (the closest
+/// approach is past the pixel, `-b / 2a > 1`, and the pixel itself is outside, `a + b + c > 0`).
+#[inline]
+fn can_skip_roots(a: f32, b: f32, c: f32) -> bool {
+    b >= 0.0 || (-b > 2.0*a && a + b + c > 0.0)
+}
+
+/// `is_shadowed` for eight pixels sharing one light, returned as a bit mask with bit `i` set if
+/// lane `i` is shadowed.
+///
+/// Every lane goes through the same operations in the same order as the scalar version, so the
+/// results match it exactly.
+pub fn is_shadowed_x8(lx: f32, ly: f32, px: f32x8, py: f32x8, cx: f32, cy: f32, r: f32) -> u32 {
+    count_tests(LANES as u64);
+    let dx = px - lx;
+    let dy = py - ly;
+    let fx = lx - cx;
+    let fy = ly - cy;
+
+    let c = fx*fx + fy*fy - r*r;
+    if c <= 0.0 {
+        return 0;
+    }
+    let c = f32x8::splat(c);
+    let a = dx*dx + dy*dy;
+    let b = 2.0 * (fx*dx + fy*dy);
+
+    let zero = f32x8::ZERO;
+    let disc = b*b - 4.0*a*c;
+    let skip = disc.cmp_lt(zero) | can_skip_roots_x8(a, b, c);
+    if skip.all() {
+        return 0;
+    }
+
+    let disc_sqrt = disc.sqrt();
+    let t1 = (-b - disc_sqrt) / (2.0*a);
+    let t2 = (-b + disc_sqrt) / (2.0*a);
+
+    let one = f32x8::ONE;
+    let hit = !skip & ((t1.cmp_ge(zero) & t1.cmp_le(one)) | (t2.cmp_ge(zero) & t2.cmp_le(one)));
+    hit.move_mask() as u32
+}
+
+/// Lane mask version of `can_skip_roots`.
+#[inline]
+fn can_skip_roots_x8(a: f32x8, b: f32x8, c: f32x8) -> f32x8 {
+    let zero = f32x8::ZERO;
+    b.cmp_ge(zero) | ((-b).cmp_gt(2.0*a) & (a + b + c).cmp_gt(zero))
+}
+
+/// Return true if the segment from (lx, ly) to (px, py) touches the box at (x, y) with size (w, h).
+///
+/// Slab test: clip the segment's `t` range against the x and y slabs in turn. A segment starting
+/// inside the box begins with `t = 0` already inside both slabs, so it always hits. Rays parallel
+/// to a slab only hit if they lie within it, edges included.
+#[allow(clippy::too_many_arguments)]
+pub fn segment_hits_rect(lx: f32, ly: f32, px: f32, py: f32, x: f32, y: f32, w: f32, h: f32) -> bool {
+    let mut t_min = 0.0f32;
+    let mut t_max = 1.0f32;
+
+    for (origin, dir, lo, hi) in [(lx, px - lx, x, x + w), (ly, py - ly, y, y + h)] {
+        if dir == 0.0 {
+            if origin < lo || origin > hi {
+                return false;
+            }
+            continue;
+        }
+
+        let t1 = (lo - origin) / dir;
+        let t2 = (hi - origin) / dir;
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_min > t_max {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn cross(ax: f32, ay: f32, bx: f32, by: f32) -> f32 {
+    ax * by - ay * bx
+}
+
+/// Return true if the segment from (lx, ly) to (px, py) intersects the wall `seg`.
+///
+/// Collinear walls that overlap the light-to-pixel segment count as blocking, so a ray running
+/// along a wall is shadowed rather than flickering between lit and dark.
+pub fn segment_blocks(lx: f32, ly: f32, px: f32, py: f32, seg: &Segment) -> bool {
+    let (rx, ry) = (px - lx, py - ly);
+    let (sx, sy) = (seg.b.0 - seg.a.0, seg.b.1 - seg.a.1);
+    let (qx, qy) = (seg.a.0 - lx, seg.a.1 - ly);
+
+    let denom = cross(rx, ry, sx, sy);
+    if denom == 0.0 {
+        // Parallel: only collinear walls can block
+        if cross(qx, qy, rx, ry) != 0.0 {
+            return false;
+        }
+        let rr = rx * rx + ry * ry;
+        if rr == 0.0 {
+            return false;
+        }
+        let t0 = (qx * rx + qy * ry) / rr;
+        let t1 = t0 + (sx * rx + sy * ry) / rr;
+        return t0.min(t1).max(0.0) <= t0.max(t1).min(1.0);
+    }
+
+    let t = cross(qx, qy, sx, sy) / denom;
+    let u = cross(qx, qy, rx, ry) / denom;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Circle of radius 20 at (100, 0), lit from the origin unless a test says otherwise
+    const CX: f32 = 100.0;
+    const CY: f32 = 0.0;
+    const R: f32 = 20.0;
+
+    #[test]
+    fn pixel_behind_circle_is_shadowed() {
+        assert!(is_shadowed(0.0, 0.0, 200.0, 0.0, CX, CY, R));
+        assert!(is_shadowed(0.0, 0.0, 200.0, 10.0, CX, CY, R));
+    }
+
+    #[test]
+    fn pixel_beside_circle_is_lit() {
+        assert!(!is_shadowed(0.0, 0.0, 200.0, 100.0, CX, CY, R));
+        assert!(!is_shadowed(0.0, 0.0, 100.0, -50.0, CX, CY, R));
+    }
+
+    #[test]
+    fn pixel_between_light_and_circle_is_lit() {
+        // The infinite line hits the circle at t = 1.6 and 2.4, past the pixel
+        assert!(!is_shadowed(0.0, 0.0, 50.0, 0.0, CX, CY, R));
+        // Just short of the near edge, and on it
+        assert!(!is_shadowed(0.0, 0.0, 79.9, 0.0, CX, CY, R));
+        assert!(is_shadowed(0.0, 0.0, 80.0, 0.0, CX, CY, R));
+    }
+
+    #[test]
+    fn pixel_on_far_side_of_light_is_lit() {
+        assert!(!is_shadowed(0.0, 0.0, -200.0, 0.0, CX, CY, R));
+    }
+
+    #[test]
+    fn tangent_ray_is_shadowed() {
+        // Touches the circle at (100, 20) with a zero discriminant; grazing counts as a hit
+        assert!(is_shadowed(0.0, 20.0, 200.0, 20.0, CX, CY, R));
+        assert!(!is_shadowed(0.0, 20.01, 200.0, 20.01, CX, CY, R));
+    }
+
+    #[test]
+    fn light_inside_circle_casts_no_shadow() {
+        // The quadratic has a root past the circle's edge on every ray, which used to shadow the
+        // whole frame
+        assert!(!is_shadowed(100.0, 0.0, 300.0, 0.0, CX, CY, R));
+        assert!(!is_shadowed(110.0, 5.0, 100.0, 300.0, CX, CY, R));
+        assert!(!is_shadowed(100.0, 0.0, 110.0, 0.0, CX, CY, R));
+        // On the edge, where one root is always t = 0
+        assert!(!is_shadowed(80.0, 0.0, 300.0, 0.0, CX, CY, R));
+        let px = f32x8::new([300.0, 100.0, -50.0, 0.0, 120.0, 200.0, 90.0, 500.0]);
+        assert_eq!(is_shadowed_x8(100.0, 0.0, px, f32x8::splat(40.0), CX, CY, R), 0);
+    }
+
+    #[cfg(feature = "intersection-stats")]
+    #[test]
+    fn every_shape_test_is_counted() {
+        // Other tests run alongside and count too, so only a lower bound holds
+        take_intersection_tests();
+        is_shadowed(0.0, 0.0, 200.0, 0.0, CX, CY, R);
+        is_shadowed_x8(0.0, 0.0, f32x8::splat(200.0), f32x8::ZERO, CX, CY, R);
+        Polygon::new(vec![(0.0, 0.0), (100.0, 0.0), (0.0, 100.0)]).blocks_ray(-50.0, 20.0, 200.0, 20.0);
+        assert!(take_intersection_tests() >= 2 + LANES as u64);
+    }
+
+    #[test]
+    fn horizontal_ray_grazing_rect_top_edge_is_blocked() {
+        let rect = Rect::new(100.0, 100.0, 50.0, 50.0);
+        assert!(rect.blocks_ray(0.0, 100.0, 200.0, 100.0));
+        assert!(!rect.blocks_ray(0.0, 99.9, 200.0, 99.9));
+    }
+
+    #[test]
+    fn light_inside_rect_casts_no_shadow() {
+        let rect = Rect::new(100.0, 100.0, 50.0, 50.0);
+        assert!(!rect.blocks_ray(125.0, 125.0, 500.0, 0.0));
+        assert!(!rect.blocks_ray(125.0, 125.0, 0.0, 400.0));
+        assert!(!rect.blocks_ray(100.0, 125.0, 500.0, 125.0));
+        assert!(segment_hits_rect(125.0, 125.0, 500.0, 0.0, 100.0, 100.0, 50.0, 50.0));
+    }
+
+    #[test]
+    fn light_inside_polygon_casts_no_shadow() {
+        let tri = Polygon::new(vec![(0.0, 0.0), (100.0, 0.0), (0.0, 100.0)]);
+        assert!(!tri.blocks_ray(20.0, 20.0, 200.0, 20.0));
+        assert!(tri.blocks_ray(-50.0, 20.0, 200.0, 20.0));
+    }
+
+    #[test]
+    fn segment_stopping_short_of_rect_is_not_blocked() {
+        let rect = Rect::new(100.0, 100.0, 50.0, 50.0);
+        assert!(!rect.blocks_ray(0.0, 125.0, 90.0, 125.0));
+        assert!(rect.blocks_ray(0.0, 125.0, 110.0, 125.0));
+    }
+
+    #[test]
+    fn crossing_segments_block() {
+        let wall = Segment::new((50.0, 0.0), (50.0, 100.0));
+        assert!(segment_blocks(0.0, 50.0, 100.0, 50.0, &wall));
+    }
+
+    #[test]
+    fn parallel_segments_do_not_block() {
+        let wall = Segment::new((0.0, 10.0), (100.0, 10.0));
+        assert!(!segment_blocks(0.0, 0.0, 100.0, 0.0, &wall));
+    }
+
+    #[test]
+    fn collinear_overlapping_segments_block() {
+        let wall = Segment::new((50.0, 0.0), (150.0, 0.0));
+        assert!(segment_blocks(0.0, 0.0, 100.0, 0.0, &wall));
+        assert!(!segment_blocks(0.0, 0.0, 40.0, 0.0, &wall));
+    }
+
+    /// `is_shadowed` without the `can_skip_roots` early-out.
+    fn is_shadowed_full(lx: f32, ly: f32, px: f32, py: f32, cx: f32, cy: f32, r: f32) -> bool {
+        let (dx, dy, fx, fy) = (px - lx, py - ly, lx - cx, ly - cy);
+        let a = dx*dx + dy*dy;
+        let b = 2.0 * (fx*dx + fy*dy);
+        let c = fx*fx + fy*fy - r*r;
+        if c <= 0.0 {
+            return false;
+        }
+        let disc = b*b - 4.0*a*c;
+        if disc < 0.0 {
+            return false;
+        }
+        let t1 = (-b - disc.sqrt()) / (2.0*a);
+        let t2 = (-b + disc.sqrt()) / (2.0*a);
+        (0.0..=1.0).contains(&t1) || (0.0..=1.0).contains(&t2)
+    }
+
+    #[test]
+    fn early_out_matches_full_shadow_test() {
+        let (cx, cy, r) = (850.0, 360.0, 150.0);
+        // Far from the circle, just outside its edge, inside it, and at its center
+        let lights = [(200.0, 360.0), (850.0, 100.0), (1001.0, 361.5), (700.5, 300.0), (850.0, 360.0)];
+        let mut skipped = 0;
+        for (lx, ly) in lights {
+            for y in (0..720).step_by(3) {
+                for x in (0..1280).step_by(3) {
+                    let (x, y) = (x as f32 + 0.5, y as f32);
+                    assert_eq!(
+                        is_shadowed(lx, ly, x, y, cx, cy, r),
+                        is_shadowed_full(lx, ly, x, y, cx, cy, r),
+                        "light ({lx}, {ly}), pixel ({x}, {y})",
+                    );
+                    let (dx, dy, fx, fy) = (x - lx, y - ly, lx - cx, ly - cy);
+                    let c = fx*fx + fy*fy - r*r;
+                    if c > 0.0 && can_skip_roots(dx*dx + dy*dy, 2.0 * (fx*dx + fy*dy), c) {
+                        skipped += 1;
+                    }
+                }
+            }
+        }
+        assert!(skipped > 0);
+    }
+
+    #[test]
+    fn batched_shadow_test_matches_scalar() {
+        let (lx, ly) = (200.0, 360.0);
+        let (cx, cy, r) = (850.0, 360.0, 150.0);
+        for y in (0..720).step_by(7) {
+            for x0 in (0..1280).step_by(LANES * 5) {
+                let xs: [f32; LANES] = std::array::from_fn(|i| (x0 + i) as f32 + 0.25);
+                let y = y as f32;
+                let mask = is_shadowed_x8(lx, ly, f32x8::new(xs), f32x8::splat(y), cx, cy, r);
+                for (i, &x) in xs.iter().enumerate() {
+                    assert_eq!(mask & (1 << i) != 0, is_shadowed(lx, ly, x, y, cx, cy, r), "at ({x}, {y})");
+                }
+            }
+        }
+        // The light itself and the circle's center, where the scalar path divides by zero or starts inside
+        let mask = is_shadowed_x8(lx, ly, f32x8::new([lx, cx, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]), f32x8::new([ly, cy, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]), cx, cy, r);
+        assert_eq!(mask & 1 != 0, is_shadowed(lx, ly, lx, ly, cx, cy, r));
+        assert_eq!(mask & 2 != 0, is_shadowed(lx, ly, cx, cy, cx, cy, r));
+    }
+
+    /// Distance from (cx, cy) to the nearest point of the segment from (lx, ly) to (px, py).
+    fn segment_distance(lx: f32, ly: f32, px: f32, py: f32, cx: f32, cy: f32) -> f32 {
+        let (dx, dy) = (px - lx, py - ly);
+        let t = (((cx - lx) * dx + (cy - ly) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+        (lx + t * dx - cx).hypot(ly + t * dy - cy)
+    }
+
+    #[test]
+    fn shadow_test_holds_with_the_light_anywhere_around_the_circle() {
+        for step in 0..72 {
+            let angle = step as f32 * 5f32.to_radians();
+            let (cos, sin) = (angle.cos(), angle.sin());
+            // The light 3R from the center, and points along (u) and across (v) its direction
+            let (lx, ly) = (CX + 3.0 * R * cos, CY + 3.0 * R * sin);
+            let at = |u: f32, v: f32| (CX + (u * cos - v * sin) * R, CY + (u * sin + v * cos) * R);
+            let shadowed = |(px, py): (f32, f32)| is_shadowed(lx, ly, px, py, CX, CY, R);
+
+            assert!(shadowed(at(-3.0, 0.0)), "straight behind, light at {step}");
+            assert!(shadowed(at(-1.5, 0.5)), "behind and off center, light at {step}");
+            assert!(shadowed(at(0.0, 0.0)), "inside, light at {step}");
+            assert!(!shadowed(at(2.0, 0.0)), "between light and circle, light at {step}");
+            assert!(!shadowed(at(1.5, 1.2)), "beside, light at {step}");
+            assert!(!shadowed(at(6.0, 0.0)), "behind the light, light at {step}");
+            assert!(!shadowed(at(-3.0, 3.0)), "behind, past the shadow's edge, light at {step}");
+            assert!(!shadowed((lx, ly)), "at the light, light at {step}");
+        }
+    }
+
+    #[test]
+    fn shadow_test_matches_segment_distance() {
+        for step in 0..36 {
+            let angle = step as f32 * 10f32.to_radians();
+            let (lx, ly) = (CX + 2.5 * R * angle.cos(), CY + 2.5 * R * angle.sin());
+            for y in (-100..=100).step_by(4) {
+                for x in (0..=200).step_by(4) {
+                    let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+                    let dist = segment_distance(lx, ly, x, y, CX, CY);
+                    // Rounding decides the grazing cases either way
+                    if (dist - R).abs() < 0.01 {
+                        continue;
+                    }
+                    assert_eq!(is_shadowed(lx, ly, x, y, CX, CY, R), dist < R, "light ({lx}, {ly}), pixel ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    fn pentagon() -> Vec<(f32, f32)> {
+        (0..5)
+            .map(|i| {
+                let a = i as f32 * std::f32::consts::TAU / 5.0;
+                (100.0 + 50.0 * a.cos(), 100.0 + 50.0 * a.sin())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangle_fills_and_blocks_in_either_winding() {
+        let ccw = Polygon::new(vec![(0.0, 0.0), (100.0, 0.0), (0.0, 100.0)]);
+        let cw = Polygon::new(vec![(0.0, 0.0), (0.0, 100.0), (100.0, 0.0)]);
+        for tri in [&ccw, &cw] {
+            assert!(tri.contains(20.0, 20.0));
+            assert!(!tri.contains(80.0, 80.0));
+            assert!(tri.blocks_ray(-50.0, 20.0, 200.0, 20.0));
+            assert!(!tri.blocks_ray(-50.0, 150.0, 200.0, 150.0));
+        }
+    }
+
+    #[test]
+    fn pentagon_fills_and_blocks_in_either_winding() {
+        let ccw = Polygon::new(pentagon());
+        let cw = Polygon::new(pentagon().into_iter().rev().collect());
+        for pent in [&ccw, &cw] {
+            assert!(pent.contains(100.0, 100.0));
+            assert!(!pent.contains(160.0, 100.0));
+            assert!(pent.blocks_ray(0.0, 100.0, 200.0, 100.0));
+            assert!(!pent.blocks_ray(0.0, 0.0, 200.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn degenerate_polygon_is_ignored() {
+        let line = Polygon::new(vec![(0.0, 0.0), (100.0, 100.0)]);
+        assert!(!line.contains(50.0, 50.0));
+        assert!(!line.blocks_ray(0.0, 100.0, 100.0, 0.0));
+        assert!(!Polygon::new(Vec::new()).blocks_ray(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn sdf_circle_shadows_like_a_circle() {
+        let sdf = Sdf::new(vec![SdfShape::Circle { x: CX, y: CY, r: R }]);
+        for (px, py) in [(200.0, 0.0), (200.0, 10.0), (200.0, 100.0), (50.0, 0.0), (-200.0, 0.0), (200.0, 30.0)] {
+            assert_eq!(sdf.blocks_ray(0.0, 0.0, px, py), is_shadowed(0.0, 0.0, px, py, CX, CY, R), "({px}, {py})");
+        }
+        assert!(!sdf.blocks_ray(CX, CY, 300.0, 0.0), "a light inside shines out");
+        assert!(!sdf.blocks_ray(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rounded_rect_distances() {
+        let shape = SdfShape::RoundedRect { x: 0.0, y: 0.0, w: 100.0, h: 60.0, radius: 10.0 };
+        assert_eq!(shape.distance(0.0, 0.0), -30.0);
+        assert_eq!(shape.distance(60.0, 0.0), 10.0);
+        assert_eq!(shape.distance(0.0, -40.0), 10.0);
+        // Out past a corner the distance is to the rounded-off arc, not the box's corner point
+        let corner = shape.distance(50.0, 30.0);
+        assert!((corner - (200f32.sqrt() - 10.0)).abs() < 1e-4, "{corner}");
+    }
+
+    #[test]
+    fn sdf_union_blocks_where_either_shape_does() {
+        let sdf = Sdf {
+            shapes: vec![
+                SdfShape::Circle { x: 100.0, y: -40.0, r: 15.0 },
+                SdfShape::RoundedRect { x: 100.0, y: 40.0, w: 30.0, h: 30.0, radius: 5.0 },
+            ],
+            steps: 32,
+            epsilon: 0.01,
+        };
+        assert!(sdf.blocks_ray(0.0, 0.0, 200.0, -80.0));
+        assert!(sdf.blocks_ray(0.0, 0.0, 200.0, 80.0));
+        assert!(!sdf.blocks_ray(0.0, 0.0, 200.0, 0.0), "straight between them");
+        assert!(sdf.contains(100.0, 50.0) && !sdf.contains(100.0, 0.0));
+        assert_eq!(sdf.bounds(), [85.0, -55.0, 115.0, 55.0]);
+    }
+}
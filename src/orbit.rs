@@ -0,0 +1,56 @@
+//! Lights that circle a point on their own.
+
+/// Moves `light` round a circle about `center`, see `World::start_orbit`.
+#[derive(Clone, Copy)]
+pub struct Orbit {
+    /// Index into the world's lights.
+    pub light: usize,
+    pub center: (f32, f32),
+    pub radius: f32,
+    /// Degrees per second, clockwise on screen as y points down; negative goes the other way.
+    pub speed: f32,
+    /// How far round the circle the light is, in degrees from the +x axis.
+    pub angle: f32,
+}
+
+impl Orbit {
+    /// Orbit of the light at `pos` about `center`, starting from where it is now. Without a
+    /// `radius` it keeps its current distance; with one it moves straight out or in to it.
+    pub fn through(light: usize, pos: (f32, f32), center: (f32, f32), radius: Option<f32>, speed: f32) -> Self {
+        let (dx, dy) = (pos.0 - center.0, pos.1 - center.1);
+        let radius = radius.unwrap_or(dx.hypot(dy));
+        Self { light, center, radius, speed, angle: dy.atan2(dx).to_degrees() }
+    }
+
+    /// Go on round the circle for `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.angle = (self.angle + self.speed * dt).rem_euclid(360.0);
+    }
+
+    /// Where the light is now.
+    pub fn position(&self) -> (f32, f32) {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        (self.center.0 + self.radius * cos, self.center.1 + self.radius * sin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orbits_start_where_the_light_is_and_come_back_round() {
+        let mut orbit = Orbit::through(0, (740.0, 360.0), (640.0, 360.0), None, 90.0);
+        assert_eq!(orbit.position(), (740.0, 360.0));
+        orbit.advance(1.0);
+        let (x, y) = orbit.position();
+        assert!((x - 640.0).abs() < 1e-3 && (y - 460.0).abs() < 1e-3, "a quarter turn on at ({x}, {y})");
+        orbit.advance(3.0);
+        let (x, y) = orbit.position();
+        assert!((x - 740.0).abs() < 1e-3 && (y - 360.0).abs() < 1e-3, "a full turn on at ({x}, {y})");
+
+        let orbit = Orbit::through(0, (640.0, 300.0), (640.0, 360.0), Some(200.0), 90.0);
+        let (x, y) = orbit.position();
+        assert!((x - 640.0).abs() < 1e-3 && (y - 160.0).abs() < 1e-3, "moved out to ({x}, {y})");
+    }
+}
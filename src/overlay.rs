@@ -0,0 +1,177 @@
+const GLYPH_W: u32 = 5;
+const GLYPH_H: u32 = 7;
+/// Blank pixels between characters and lines, and around the backing box.
+const SPACING: u32 = 1;
+
+const TEXT_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+/// Backing box behind the text, blended at `BACKGROUND_ALPHA` so the scene shows through.
+const BACKGROUND: [u8; 3] = [0x00, 0x00, 0x00];
+const BACKGROUND_ALPHA: u32 = 160;
+
+/// Rows of a 5x7 glyph, top to bottom, with the leftmost pixel in bit 4.
+/// Lowercase letters share the uppercase shapes; anything else unknown renders blank.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '|' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        _ => [0x00; 7],
+    }
+}
+
+/// Draw `text` into an RGBA `frame` of the given `width`, with its top-left corner at (x, y).
+///
+/// Each font pixel covers `scale`×`scale` frame pixels, and lines are split on `\n`. A
+/// translucent box is drawn behind the text so it stays readable over any part of the scene.
+/// Anything falling outside the frame is clipped.
+pub fn draw_text(frame: &mut [u8], width: u32, x: u32, y: u32, text: &str, scale: u32) {
+    let height = frame.len() as u32 / 4 / width;
+    let scale = scale.max(1);
+    if text.lines().all(str::is_empty) {
+        return;
+    }
+
+    let advance_x = (GLYPH_W + SPACING) * scale;
+    let advance_y = (GLYPH_H + SPACING) * scale;
+    let (box_w, box_h) = text_size(text, scale);
+
+    let mut put = |px: u32, py: u32, rgb: [u8; 3], alpha: u32| {
+        if px >= width || py >= height {
+            return;
+        }
+        let i = ((py * width + px) * 4) as usize;
+        for (dst, src) in frame[i..i + 3].iter_mut().zip(rgb) {
+            *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+        }
+    };
+
+    for py in y..y + box_h {
+        for px in x..x + box_w {
+            put(px, py, BACKGROUND, BACKGROUND_ALPHA);
+        }
+    }
+
+    for (row, line) in text.lines().enumerate() {
+        let top = y + SPACING * scale + row as u32 * advance_y;
+        for (col, c) in line.chars().enumerate() {
+            let left = x + SPACING * scale + col as u32 * advance_x;
+            for (gy, bits) in glyph(c).into_iter().enumerate() {
+                for gx in 0..GLYPH_W {
+                    if bits & (0x10 >> gx) == 0 {
+                        continue;
+                    }
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            put(left + gx * scale + sx, top + gy as u32 * scale + sy, TEXT_COLOR, 255);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Width and height in frame pixels of the box `draw_text` draws for `text` at `scale`.
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let scale = scale.max(1);
+    let cols = text.lines().map(|l| l.chars().count() as u32).max().unwrap_or(0);
+    let rows = text.lines().count() as u32;
+    (cols * (GLYPH_W + SPACING) * scale + SPACING * scale, rows * (GLYPH_H + SPACING) * scale + SPACING * scale)
+}
+
+/// Blend one pixel wide lines `spacing` frame pixels apart over an RGBA `frame` of the given
+/// `width`, running through `origin` both across and down, at `alpha` out of 255.
+pub fn draw_grid(frame: &mut [u8], width: u32, origin: (f32, f32), spacing: f32, rgb: [u8; 3], alpha: u32) {
+    let height = frame.len() as u32 / 4 / width;
+    // Positions of the lines within 0..len, from the first at or after 0
+    let lines = |start: f32, len: u32| {
+        let first = start - (start / spacing).floor() * spacing;
+        (0..).map(move |k| (first + k as f32 * spacing).round() as u32).take_while(move |&p| p < len)
+    };
+    let columns: Vec<u32> = lines(origin.0, width).collect();
+    let rows: Vec<u32> = lines(origin.1, height).collect();
+    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        if columns.binary_search(&x).is_ok() || rows.binary_search(&y).is_ok() {
+            for (dst, src) in pixel[..3].iter_mut().zip(rgb) {
+                *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+            }
+        }
+    }
+}
+
+/// Draw a one pixel wide line from (x0, y0) to (x1, y1) into an RGBA `frame` of the given `width`,
+/// opaque and clipped to the frame.
+pub fn draw_line(frame: &mut [u8], width: u32, (x0, y0): (i32, i32), (x1, y1): (i32, i32), rgb: [u8; 3]) {
+    let (width, height) = (width as i32, (frame.len() as u32 / 4 / width) as i32);
+    // Bresenham's algorithm, stepping whichever axis the accumulated error says is due
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let mut err = dx + dy;
+    let (mut x, mut y) = (x0, y0);
+    loop {
+        if (0..width).contains(&x) && (0..height).contains(&y) {
+            let i = (y * width + x) as usize * 4;
+            frame[i..i + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], 0xff]);
+        }
+        if (x, y) == (x1, y1) {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += step_x;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += step_y;
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use log::warn;
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Captured frames are shrunk by this factor in each dimension.
+const GIF_DOWNSCALE: u32 = 2;
+/// Capture rate, independent of how fast frames are rendered.
+const GIF_FPS: u32 = 20;
+/// Hard cap on captured frames so a forgotten recording can't eat all memory.
+const GIF_MAX_FRAMES: usize = 300;
+
+/// Collects downscaled frames in memory until written out as a looping GIF.
+pub struct Recorder {
+    width: u32,
+    height: u32,
+    frames: Vec<Vec<u8>>,
+    last_capture: Option<Instant>,
+    /// Set once the frame cap has been hit and warned about.
+    capped: bool,
+}
+
+impl Recorder {
+    /// Start a recording of frames rendered at `width`×`height`.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frames: Vec::new(),
+            last_capture: None,
+            capped: false,
+        }
+    }
+
+    fn out_size(&self) -> (u32, u32) {
+        ((self.width / GIF_DOWNSCALE).max(1), (self.height / GIF_DOWNSCALE).max(1))
+    }
+
+    /// Add `frame` to the recording if it's time for the next capture.
+    pub fn capture(&mut self, frame: &[u8]) {
+        let interval = Duration::from_secs(1) / GIF_FPS;
+        if self.last_capture.is_some_and(|t| t.elapsed() < interval) {
+            return;
+        }
+        if self.frames.len() >= GIF_MAX_FRAMES {
+            if !self.capped {
+                let (w, h) = self.out_size();
+                let mb = (GIF_MAX_FRAMES * (w * h * 4) as usize) as f32 / (1024.0 * 1024.0);
+                warn!("Recording hit the {GIF_MAX_FRAMES} frame cap ({mb:.0} MB), dropping further frames");
+                self.capped = true;
+            }
+            return;
+        }
+        self.last_capture = Some(Instant::now());
+        self.frames.push(self.downscale(frame));
+    }
+
+    /// Box-filter `frame` down by `GIF_DOWNSCALE`.
+    fn downscale(&self, frame: &[u8]) -> Vec<u8> {
+        let (w, h) = self.out_size();
+        let n = GIF_DOWNSCALE * GIF_DOWNSCALE;
+        let mut out = Vec::with_capacity((w * h * 4) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let mut sum = [0u32; 4];
+                for dy in 0..GIF_DOWNSCALE {
+                    for dx in 0..GIF_DOWNSCALE {
+                        let sx = x * GIF_DOWNSCALE + dx;
+                        let sy = y * GIF_DOWNSCALE + dy;
+                        let i = ((sy * self.width + sx) * 4) as usize;
+                        for (s, &c) in sum.iter_mut().zip(&frame[i..i + 4]) {
+                            *s += c as u32;
+                        }
+                    }
+                }
+                out.extend(sum.map(|s| (s / n) as u8));
+            }
+        }
+        out
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Encode the captured frames to `path` as an endlessly looping GIF.
+    pub fn finish(self, path: &Path) -> Result<(), gif::EncodingError> {
+        let (w, h) = self.out_size();
+        let mut encoder = gif::Encoder::new(File::create(path)?, w as u16, h as u16, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for mut rgba in self.frames {
+            let mut frame = gif::Frame::from_rgba_speed(w as u16, h as u16, &mut rgba, 10);
+            frame.delay = (100 / GIF_FPS) as u16;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
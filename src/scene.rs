@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::light::Light;
+use crate::occluder::{Circle, Polygon, Rect, Sdf, SdfShape, Segment};
+use crate::timeline::Keyframe;
+
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+
+const CIRCLE_X: f32 = 850.0;
+const CIRCLE_Y: f32 = 720.0/2.0;
+const CIRCLE_R: f32 = 150.0;
+const CIRCLE_VY: f32 = 100.0; // Speed in pixels per second
+
+const LIGHT_X: f32 = 200.0;
+const LIGHT_Y: f32 = 720.0/2.0;
+const LIGHT_R: f32 = 25.0;
+const LIGHT_COLOR: [u8; 3] = [0xff, 0xff, 0x00];
+
+/// Minimum brightness of any pixel, so shadows read as dark blue-grey instead of black.
+const AMBIENT: [u8; 3] = [20, 20, 30];
+
+/// Rects, walls and polygons are solid white by default, standing out against lit and shadowed
+/// ground alike.
+const FILL: [u8; 3] = [0xff; 3];
+
+/// Circles move at constant speed by default, bouncing off the edges without losing any.
+const GRAVITY: f32 = 0.0;
+const RESTITUTION: f32 = 1.0;
+
+/// How strongly a floor reflects the circles unless the scene says otherwise.
+const FLOOR_REFLECTION: f32 = 0.3;
+
+/// Reflections followed off mirror circles by default, and the most a scene may ask for.
+const MAX_BOUNCES: u32 = 1;
+const MAX_BOUNCES_LIMIT: u32 = 16;
+
+/// Everything needed to build a `World`, loadable from JSON.
+///
+/// Lit areas take the colors of the lights reaching them; `ambient` is the background color
+/// left where no light reaches, and `fill` the color rects, walls, polygons and SDF shapes are
+/// painted in.
+///
+/// Fields missing from a file fall back to the built-in scene's.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub width: u32,
+    pub height: u32,
+    pub ambient: [u8; 3],
+    pub fill: [u8; 3],
+    /// Top and bottom colors of a vertical gradient painted on the background over the scene's
+    /// height. Light and ambient are filtered by it, like a circle's albedo. Without one the
+    /// background takes the light as it is, as if white.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<[[u8; 3]; 2]>,
+    /// How many times a view ray may reflect off circles with a reflective material before it
+    /// only sees their own shading. Zero turns reflections off.
+    pub max_bounces: u32,
+    /// Downward acceleration of the circles in pixels per second², zero for constant speed.
+    pub gravity: f32,
+    /// Fraction of a circle's speed kept when it bounces off the top or bottom edge.
+    pub restitution: f32,
+    pub lights: Vec<Light>,
+    pub circles: Vec<Circle>,
+    pub rects: Vec<Rect>,
+    pub walls: Vec<Segment>,
+    pub polygons: Vec<Polygon>,
+    /// Shapes made of circles and rounded rects given by their distance functions, see `Sdf`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sdfs: Vec<Sdf>,
+    /// Scripted positions for the lights and circles over time, in time order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keyframes: Vec<Keyframe>,
+    /// A horizontal line the circles stand over, see `Floor`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub floor: Option<Floor>,
+    /// The region the circles bounce around in, the whole scene without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Bounds>,
+    /// Have circles leaving the region come back in on the opposite side instead of bouncing.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub wrap: bool,
+}
+
+/// The edges of a play area, in scene pixels. Circles bounce off its sides from the inside, or
+/// with `Scene::wrap` go out through one and back in through the other; lights and the other
+/// shapes may still be anywhere.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+/// A floor line across the scene at height `y`. Below it, the ground darkens under circles
+/// close to touching it, and shows each circle above mirrored upside down at `reflection` of
+/// its strength, fading out over the circle's own height. Circles aren't stopped by it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Floor {
+    pub y: f32,
+    #[serde(default = "floor_reflection")]
+    pub reflection: f32,
+}
+
+fn floor_reflection() -> f32 {
+    FLOOR_REFLECTION
+}
+
+impl Floor {
+    /// A floor at `y` with the default reflection strength.
+    pub fn new(y: f32) -> Self {
+        Self { y, reflection: FLOOR_REFLECTION }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+            ambient: AMBIENT,
+            fill: FILL,
+            gradient: None,
+            max_bounces: MAX_BOUNCES,
+            gravity: GRAVITY,
+            restitution: RESTITUTION,
+            lights: vec![Light::new(LIGHT_X, LIGHT_Y, LIGHT_R, LIGHT_COLOR)],
+            circles: vec![Circle::new(CIRCLE_X, CIRCLE_Y, CIRCLE_R, CIRCLE_VY)],
+            rects: Vec::new(),
+            walls: Vec::new(),
+            polygons: Vec::new(),
+            sdfs: Vec::new(),
+            keyframes: Vec::new(),
+            floor: None,
+            bounds: None,
+            wrap: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(_) => write!(f, "could not access scene file"),
+            SceneError::Json(_) => write!(f, "scene is not valid JSON"),
+            SceneError::Invalid(msg) => write!(f, "invalid scene: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Json(err) => Some(err),
+            SceneError::Invalid(_) => None,
+        }
+    }
+}
+
+impl Scene {
+    /// Read and validate a scene from the JSON file at `path`.
+    pub fn load(path: &Path) -> Result<Self, SceneError> {
+        let json = fs::read_to_string(path).map_err(SceneError::Io)?;
+        let scene: Scene = serde_json::from_str(&json).map_err(SceneError::Json)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Write the scene to `path` as pretty-printed JSON that `load` reads back unchanged.
+    pub fn save(&self, path: &Path) -> Result<(), SceneError> {
+        let json = serde_json::to_string_pretty(self).map_err(SceneError::Json)?;
+        fs::write(path, json).map_err(SceneError::Io)
+    }
+
+    /// Replace the circles with a `cols`×`rows` grid of copies of the first one, `spacing` apart
+    /// between centers and centered on the middle of the scene, for stress-testing shadows with
+    /// many shapes. The copies keep its speed and material, shrunk if need be so neighbours
+    /// don't touch. A scene without circles copies the default one.
+    pub fn arrange_circles(&mut self, cols: u32, rows: u32, spacing: f32) {
+        let mut circle = self.circles.first().cloned().unwrap_or_else(|| Circle::new(CIRCLE_X, CIRCLE_Y, CIRCLE_R, CIRCLE_VY));
+        circle.r = circle.r.min(spacing * 0.4);
+        let (cx, cy) = (self.width as f32 / 2.0, self.height as f32 / 2.0);
+        let offset = |i: u32, n: u32| (i as f32 - (n - 1) as f32 / 2.0) * spacing;
+        self.circles = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .map(|(col, row)| Circle { x: cx + offset(col, cols), y: cy + offset(row, rows), ..circle.clone() })
+            .collect();
+    }
+
+    /// `bounds`, or else the whole scene.
+    pub fn play_area(&self) -> Bounds {
+        self.bounds.unwrap_or(Bounds { left: 0.0, top: 0.0, right: self.width as f32, bottom: self.height as f32 })
+    }
+
+    /// Check that every size is positive and every position finite.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        fn check(ok: bool, what: impl FnOnce() -> String) -> Result<(), SceneError> {
+            if ok { Ok(()) } else { Err(SceneError::Invalid(what())) }
+        }
+        let finite = |x: f32, y: f32| x.is_finite() && y.is_finite();
+
+        check(self.width > 0 && self.height > 0, || format!("size {}x{} must be non-zero", self.width, self.height))?;
+        check(self.max_bounces <= MAX_BOUNCES_LIMIT, || format!("max_bounces {} must be at most {MAX_BOUNCES_LIMIT}", self.max_bounces))?;
+        check(self.gravity.is_finite(), || format!("gravity {} must be finite", self.gravity))?;
+        check((0.0..=1.0).contains(&self.restitution), || format!("restitution {} must be between 0 and 1", self.restitution))?;
+        for (i, light) in self.lights.iter().enumerate() {
+            check(finite(light.x, light.y), || format!("light {i} has a non-finite position"))?;
+            check(light.r > 0.0, || format!("light {i} radius {} must be positive", light.r))?;
+            check(light.intensity >= 0.0, || format!("light {i} intensity {} must not be negative", light.intensity))?;
+            check((0.0..=1.0).contains(&light.flicker_amplitude), || format!("light {i} flicker_amplitude {} must be between 0 and 1", light.flicker_amplitude))?;
+            if let Some(cone) = &light.cone {
+                check(cone.direction.is_finite(), || format!("light {i} cone direction {} must be finite", cone.direction))?;
+                check((0.0..=180.0).contains(&cone.half_angle), || format!("light {i} cone half_angle {} must be between 0 and 180", cone.half_angle))?;
+            }
+        }
+        for (i, circle) in self.circles.iter().enumerate() {
+            check(finite(circle.x, circle.y) && finite(circle.vx, circle.vy), || format!("circle {i} has a non-finite position or speed"))?;
+            check(circle.r > 0.0, || format!("circle {i} radius {} must be positive", circle.r))?;
+            let m = &circle.material;
+            check(m.shininess >= 0.0, || format!("circle {i} shininess {} must not be negative", m.shininess))?;
+            check((0.0..=1.0).contains(&m.reflectivity), || format!("circle {i} reflectivity {} must be between 0 and 1", m.reflectivity))?;
+            check((0.0..=1.0).contains(&circle.opacity), || format!("circle {i} opacity {} must be between 0 and 1", circle.opacity))?;
+        }
+        for (i, rect) in self.rects.iter().enumerate() {
+            check(finite(rect.x, rect.y), || format!("rect {i} has a non-finite position"))?;
+            check(rect.w > 0.0 && rect.h > 0.0, || format!("rect {i} size {}x{} must be positive", rect.w, rect.h))?;
+        }
+        for (i, wall) in self.walls.iter().enumerate() {
+            check(finite(wall.a.0, wall.a.1) && finite(wall.b.0, wall.b.1), || format!("wall {i} has a non-finite endpoint"))?;
+        }
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            check(polygon.verts.iter().all(|&(x, y)| finite(x, y)), || format!("polygon {i} has a non-finite vertex"))?;
+        }
+        for (i, sdf) in self.sdfs.iter().enumerate() {
+            check(sdf.steps > 0 && sdf.epsilon > 0.0, || format!("sdf {i} steps {} and epsilon {} must be positive", sdf.steps, sdf.epsilon))?;
+            for shape in &sdf.shapes {
+                let ok = match *shape {
+                    SdfShape::Circle { x, y, r } => finite(x, y) && r > 0.0,
+                    SdfShape::RoundedRect { x, y, w, h, radius } => finite(x, y) && w > 0.0 && h > 0.0 && radius >= 0.0,
+                };
+                check(ok, || format!("sdf {i} has a shape with a non-finite position or a size that isn't positive"))?;
+            }
+        }
+        if let Some(b) = &self.bounds {
+            check([b.left, b.top, b.right, b.bottom].iter().all(|v| v.is_finite()), || "bounds must be finite".to_string())?;
+            check(b.left < b.right && b.top < b.bottom, || format!("bounds {}..{} by {}..{} must not be empty", b.left, b.right, b.top, b.bottom))?;
+        }
+        if let Some(floor) = &self.floor {
+            check(floor.y.is_finite(), || format!("floor y {} must be finite", floor.y))?;
+            check((0.0..=1.0).contains(&floor.reflection), || format!("floor reflection {} must be between 0 and 1", floor.reflection))?;
+        }
+        let mut last = f32::NEG_INFINITY;
+        for (i, keyframe) in self.keyframes.iter().enumerate() {
+            check(keyframe.time.is_finite() && keyframe.time >= last, || format!("keyframe {i} time {} must come after the one before", keyframe.time))?;
+            last = keyframe.time;
+            check(keyframe.lights.len() <= self.lights.len(), || format!("keyframe {i} places {} lights but there are {}", keyframe.lights.len(), self.lights.len()))?;
+            check(keyframe.circles.len() <= self.circles.len(), || format!("keyframe {i} places {} circles but there are {}", keyframe.circles.len(), self.circles.len()))?;
+            check(keyframe.lights.iter().chain(&keyframe.circles).all(|&[x, y]| finite(x, y)), || format!("keyframe {i} has a non-finite position"))?;
+        }
+        Ok(())
+    }
+}
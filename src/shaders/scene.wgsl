@@ -0,0 +1,677 @@
+// GPU port of `World::shade`/`World::draw`. Keep the math in step with the CPU renderer so both
+// produce the same image.
+
+struct Params {
+    // Where the scene lands on the surface: x, y, width, height in surface pixels
+    clip: vec4<f32>,
+    // Ambient color, rgb in 0..1; w unused
+    ambient: vec4<f32>,
+    // Scene width, scene height, falloff, surface pixels per scene pixel
+    misc: vec4<f32>,
+    // Number of lights, circles, rects, walls
+    counts: vec4<u32>,
+    // Number of polygons, shadow samples, supersampling factor, FLAG_* bits
+    options: vec4<u32>,
+    // Maximum reflections off mirror circles, number of SDF shapes; zw unused
+    bounces: vec4<u32>,
+    // World position of the top-left scene pixel, world units per scene pixel; w unused. See
+    // `Camera::transform`
+    camera: vec4<f32>,
+    // Background gradient top and bottom colors, rgb in 0..1, or white for none. The w
+    // components are the scene width and height the gradient or background image spans; see
+    // `World::background_albedo`
+    gradient_top: vec4<f32>,
+    gradient_bottom: vec4<f32>,
+    // Color of rects, walls and polygons, rgb in 0..1; w unused
+    fill: vec4<f32>,
+    // Vignette strength and radius; zw unused
+    vignette: vec4<f32>,
+    // Floor height and reflection strength, with FLAG_FLOOR; zw unused
+    floor: vec4<f32>,
+}
+
+struct Light {
+    // x, y, radius, intensity this frame
+    pos: vec4<f32>,
+    // rgb in 0..1, unused
+    color: vec4<f32>,
+    // Cone direction and half angle in radians, pi for none; zw unused. See `Cone`
+    cone: vec4<f32>,
+    // rgb left in the light's shadows, see `Light::ambient`; w unused
+    ambient: vec4<f32>,
+}
+
+struct Sdf {
+    // First shape in `sdf_shapes`, shape count, steps, epsilon's bits
+    info: vec4<u32>,
+}
+
+struct SdfShape {
+    // Center x, y, half width, half height
+    pos: vec4<f32>,
+    // Corner radius; yzw unused. A circle is a square with its corners rounded all the way
+    round: vec4<f32>,
+}
+
+struct Circle {
+    // x, y, radius, shininess
+    pos: vec4<f32>,
+    // rgb in 0..1, reflectivity
+    albedo: vec4<f32>,
+    // rgb in 0..1, opacity
+    emissive: vec4<f32>,
+}
+
+const FLAG_GAMMA: u32 = 1u;
+const FLAG_TINT_LIGHTS: u32 = 2u;
+const FLAG_SRGB_TARGET: u32 = 4u;
+const FLAG_SRGB_OVERLAY: u32 = 8u;
+const FLAG_PENUMBRA: u32 = 16u;
+const FLAG_BACKGROUND_IMAGE: u32 = 32u;
+const FLAG_TONEMAP: u32 = 64u;
+const FLAG_DITHER: u32 = 128u;
+const FLAG_NORMALS: u32 = 256u;
+const FLAG_EDGE_AA: u32 = 512u;
+const FLAG_VIGNETTE: u32 = 1024u;
+const FLAG_FLOOR: u32 = 2048u;
+const FLAG_INVERT: u32 = 4096u;
+const RIM_INSET: f32 = 1e-3;
+// See `world::CONTACT_DEPTH` and `world::CONTACT_STRENGTH`
+const CONTACT_DEPTH: f32 = 0.25;
+const CONTACT_STRENGTH: f32 = 0.6;
+
+const GOLDEN_ANGLE: f32 = 2.399963;
+const TAU: f32 = 6.2831853;
+// See `color::BAYER4`, row by row
+var<private> BAYER4: array<u32, 16> = array<u32, 16>(0u, 8u, 2u, 10u, 12u, 4u, 14u, 6u, 3u, 11u, 1u, 9u, 15u, 7u, 13u, 5u);
+
+// See `light::BLUE_NOISE_MAX`
+const BLUE_NOISE_MAX: u32 = 64u;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> lights: array<Light>;
+@group(0) @binding(2) var<storage, read> circles: array<Circle>;
+// x, y, width, height
+@group(0) @binding(3) var<storage, read> rects: array<vec4<f32>>;
+// a.x, a.y, b.x, b.y
+@group(0) @binding(4) var<storage, read> walls: array<vec4<f32>>;
+// First vertex in `verts`, vertex count
+@group(0) @binding(5) var<storage, read> polygons: array<vec2<u32>>;
+@group(0) @binding(6) var<storage, read> verts: array<vec2<f32>>;
+// The CPU frame buffer, holding only the text overlay (premultiplied) in GPU mode
+@group(0) @binding(7) var overlay: texture_2d<f32>;
+// Unencoded sRGB texels, a 1×1 placeholder without FLAG_BACKGROUND_IMAGE
+@group(0) @binding(8) var background: texture_2d<f32>;
+// `light::blue_noise` for the shadow sample count, on the unit disk, when it's at most
+// BLUE_NOISE_MAX
+@group(0) @binding(9) var<storage, read> disk_samples: array<vec2<f32>>;
+@group(0) @binding(10) var<storage, read> sdfs: array<Sdf>;
+@group(0) @binding(11) var<storage, read> sdf_shapes: array<SdfShape>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    // One full-screen triangle
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn has_flag(flag: u32) -> bool {
+    return (params.options.w & flag) != 0u;
+}
+
+// Diagnostic views show their values as they are, see `World::encode`
+fn diagnostic() -> bool {
+    return has_flag(FLAG_PENUMBRA) || has_flag(FLAG_NORMALS);
+}
+
+fn srgb_encode(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_decode(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+// See `color::aces`
+fn aces(c: vec3<f32>) -> vec3<f32> {
+    let x = max(c, vec3<f32>(0.0));
+    return min(x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(1.0));
+}
+
+fn cross2(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+// See `is_shadowed`. Like every shape, a circle casts no shadow from a light inside it
+fn circle_blocks(l: vec2<f32>, p: vec2<f32>, c: vec4<f32>) -> bool {
+    let d = p - l;
+    let f = l - c.xy;
+    let cc = dot(f, f) - c.z * c.z;
+    if cc <= 0.0 {
+        return false;
+    }
+    let a = dot(d, d);
+    let b = 2.0 * dot(f, d);
+    let disc = b * b - 4.0 * a * cc;
+    if disc < 0.0 {
+        return false;
+    }
+    let s = sqrt(disc);
+    let t1 = (-b - s) / (2.0 * a);
+    let t2 = (-b + s) / (2.0 * a);
+    return (t1 >= 0.0 && t1 <= 1.0) || (t2 >= 0.0 && t2 <= 1.0);
+}
+
+// See `segment_hits_rect`
+fn rect_blocks(l: vec2<f32>, p: vec2<f32>, r: vec4<f32>) -> bool {
+    let lo = r.xy;
+    let hi = r.xy + r.zw;
+    if all(l >= lo) && all(l <= hi) {
+        return false;
+    }
+    let dir = p - l;
+    var t_min = 0.0;
+    var t_max = 1.0;
+    for (var k = 0; k < 2; k++) {
+        if dir[k] == 0.0 {
+            if l[k] < lo[k] || l[k] > hi[k] {
+                return false;
+            }
+            continue;
+        }
+        let t1 = (lo[k] - l[k]) / dir[k];
+        let t2 = (hi[k] - l[k]) / dir[k];
+        t_min = max(t_min, min(t1, t2));
+        t_max = min(t_max, max(t1, t2));
+        if t_min > t_max {
+            return false;
+        }
+    }
+    return true;
+}
+
+// See `segment_blocks`
+fn segment_blocks(l: vec2<f32>, p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> bool {
+    let r = p - l;
+    let s = b - a;
+    let q = a - l;
+    let denom = cross2(r, s);
+    if denom == 0.0 {
+        if cross2(q, r) != 0.0 {
+            return false;
+        }
+        let rr = dot(r, r);
+        if rr == 0.0 {
+            return false;
+        }
+        let t0 = dot(q, r) / rr;
+        let t1 = t0 + dot(s, r) / rr;
+        return max(min(t0, t1), 0.0) <= min(max(t0, t1), 1.0);
+    }
+    let t = cross2(q, s) / denom;
+    let u = cross2(q, r) / denom;
+    return t >= 0.0 && t <= 1.0 && u >= 0.0 && u <= 1.0;
+}
+
+fn polygon_vert(first: u32, n: u32, i: u32) -> vec2<f32> {
+    return verts[first + i % n];
+}
+
+fn polygon_contains(poly: vec2<u32>, p: vec2<f32>) -> bool {
+    if poly.y < 3u {
+        return false;
+    }
+    var winding = 0.0;
+    for (var i = 0u; i < poly.y; i++) {
+        let a = polygon_vert(poly.x, poly.y, i);
+        let b = polygon_vert(poly.x, poly.y, i + 1u);
+        let side = cross2(b - a, p - a);
+        if side == 0.0 {
+            continue;
+        }
+        if winding == 0.0 {
+            winding = sign(side);
+        } else if sign(side) != winding {
+            return false;
+        }
+    }
+    return true;
+}
+
+fn polygon_blocks(poly: vec2<u32>, l: vec2<f32>, p: vec2<f32>) -> bool {
+    if poly.y < 3u || polygon_contains(poly, l) {
+        return false;
+    }
+    for (var i = 0u; i < poly.y; i++) {
+        if segment_blocks(l, p, polygon_vert(poly.x, poly.y, i), polygon_vert(poly.x, poly.y, i + 1u)) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// See `SdfShape::distance`
+fn sdf_distance(sdf: Sdf, p: vec2<f32>) -> f32 {
+    var d = 3.4028235e38;
+    for (var i = sdf.info.x; i < sdf.info.x + sdf.info.y; i++) {
+        let s = sdf_shapes[i];
+        let q = abs(p - s.pos.xy) - (s.pos.zw - s.round.x);
+        d = min(d, length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - s.round.x);
+    }
+    return d;
+}
+
+// See `Sdf::blocks_ray`
+fn sdf_blocks(sdf: Sdf, l: vec2<f32>, p: vec2<f32>) -> bool {
+    if sdf_distance(sdf, l) <= 0.0 {
+        return false;
+    }
+    let len = distance(l, p);
+    let dir = (p - l) / len;
+    let epsilon = bitcast<f32>(sdf.info.w);
+    var t = 0.0;
+    for (var i = 0u; i < sdf.info.z; i++) {
+        if t >= len {
+            return false;
+        }
+        let d = sdf_distance(sdf, l + dir * t);
+        if d < epsilon {
+            return true;
+        }
+        t += d;
+    }
+    return t < len;
+}
+
+fn inside_other_occluder(p: vec2<f32>) -> bool {
+    for (var i = 0u; i < params.counts.z; i++) {
+        let r = rects[i];
+        if p.x >= r.x && p.x <= r.x + r.z && p.y >= r.y && p.y <= r.y + r.w {
+            return true;
+        }
+    }
+    for (var i = 0u; i < params.options.x; i++) {
+        if polygon_contains(polygons[i], p) {
+            return true;
+        }
+    }
+    for (var i = 0u; i < params.bounces.y; i++) {
+        if sdf_distance(sdfs[i], p) <= 0.0 {
+            return true;
+        }
+    }
+    return false;
+}
+
+// See `World::transmittance`
+fn transmittance(l: vec2<f32>, p: vec2<f32>) -> vec3<f32> {
+    for (var i = 0u; i < params.counts.w; i++) {
+        if segment_blocks(l, p, walls[i].xy, walls[i].zw) {
+            return vec3<f32>(0.0);
+        }
+    }
+    for (var i = 0u; i < params.counts.z; i++) {
+        if rect_blocks(l, p, rects[i]) {
+            return vec3<f32>(0.0);
+        }
+    }
+    for (var i = 0u; i < params.options.x; i++) {
+        if polygon_blocks(polygons[i], l, p) {
+            return vec3<f32>(0.0);
+        }
+    }
+    for (var i = 0u; i < params.bounces.y; i++) {
+        if sdf_blocks(sdfs[i], l, p) {
+            return vec3<f32>(0.0);
+        }
+    }
+    var through = vec3<f32>(1.0);
+    for (var i = 0u; i < params.counts.y; i++) {
+        let c = circles[i];
+        if circle_blocks(l, p, c.pos) {
+            if c.emissive.w >= 1.0 {
+                return vec3<f32>(0.0);
+            }
+            through *= (1.0 - c.emissive.w) * linear(c.albedo.rgb);
+        }
+    }
+    return through;
+}
+
+// See `Light::sample`
+fn light_sample(light: Light, i: u32, n: u32) -> vec2<f32> {
+    if n <= 1u {
+        return light.pos.xy;
+    }
+    if n <= BLUE_NOISE_MAX {
+        return light.pos.xy + light.pos.z * disk_samples[i];
+    }
+    let r = light.pos.z * sqrt((f32(i) + 0.5) / f32(n));
+    let theta = f32(i) * GOLDEN_ANGLE;
+    return light.pos.xy + r * vec2<f32>(cos(theta), sin(theta));
+}
+
+fn visibility(light: Light, p: vec2<f32>) -> vec3<f32> {
+    let n = max(params.options.y, 1u);
+    var through = vec3<f32>(0.0);
+    for (var i = 0u; i < n; i++) {
+        through += transmittance(light_sample(light, i, n), p);
+    }
+    return through / f32(n);
+}
+
+fn linear(c: vec3<f32>) -> vec3<f32> {
+    if has_flag(FLAG_GAMMA) {
+        return srgb_decode(c);
+    }
+    return c;
+}
+
+// See `Light::shines_on`
+fn shines_on(light: Light, p: vec2<f32>) -> bool {
+    let d = p - light.pos.xy;
+    let off = abs(atan2(d.y, d.x) - light.cone.x) % TAU;
+    return min(off, TAU - off) <= light.cone.y;
+}
+
+// See `sphere_normal` in world.rs. `sphere` holds x, y and radius
+fn sphere_normal(sphere: vec3<f32>, p: vec2<f32>) -> vec3<f32> {
+    let n = (p - sphere.xy) / sphere.z;
+    return vec3<f32>(n, sqrt(max(1.0 - dot(n, n), 0.0)));
+}
+
+// See `World::shade_sphere`. `pos` holds x, y, radius and shininess; colors are unconverted
+fn shade_sphere(pos: vec4<f32>, albedo_rgb: vec3<f32>, emissive: vec3<f32>, p: vec2<f32>) -> vec3<f32> {
+    let normal = sphere_normal(pos.xyz, p);
+    let n = normal.xy;
+    let nz = normal.z;
+    let albedo = linear(albedo_rgb);
+    var rgb = linear(emissive);
+    for (var i = 0u; i < params.counts.x; i++) {
+        let l = lights[i].pos.xy - p;
+        let l_len = length(l);
+        if l_len == 0.0 || !shines_on(lights[i], p) {
+            continue;
+        }
+        let diffuse = max(dot(n, l / l_len), 0.0);
+        if diffuse == 0.0 {
+            continue;
+        }
+        var specular = 0.0;
+        if pos.w > 0.0 {
+            specular = pow(max((dot(n, l / l_len) + nz) / sqrt(2.0), 0.0), pos.w);
+        }
+        rgb += linear(lights[i].color.rgb) * lights[i].pos.w * (albedo * diffuse + specular);
+    }
+    return max(rgb, linear(params.ambient.rgb) * albedo);
+}
+
+// See `World::shade_with`, minus the shared lookups for light disks and shapes
+fn background_albedo(p: vec2<f32>) -> vec3<f32> {
+    let extent = vec2<f32>(params.gradient_top.w, params.gradient_bottom.w);
+    if has_flag(FLAG_BACKGROUND_IMAGE) {
+        let size = vec2<f32>(textureDimensions(background));
+        let texel = min(vec2<u32>(max(p / extent * size, vec2<f32>(0.0))), vec2<u32>(size) - 1u);
+        return linear(textureLoad(background, texel, 0).rgb);
+    }
+    let t = clamp(p.y / extent.y, 0.0, 1.0);
+    return mix(linear(params.gradient_top.rgb), linear(params.gradient_bottom.rgb), t);
+}
+
+fn shade_background(p: vec2<f32>) -> vec3<f32> {
+    var rgb = vec3<f32>(0.0);
+    for (var i = 0u; i < params.counts.x; i++) {
+        let light = lights[i];
+        if !shines_on(light, p) {
+            continue;
+        }
+        let visible = visibility(light, p);
+        let shadowed = any(light.ambient.rgb != vec3<f32>(0.0));
+        if all(visible == vec3<f32>(0.0)) && !shadowed {
+            continue;
+        }
+        let d = p - light.pos.xy;
+        let attenuation = visible / (1.0 + params.misc.z * dot(d, d));
+        rgb += linear(light.color.rgb) * light.pos.w * attenuation;
+        if shadowed {
+            rgb += linear(light.ambient.rgb) * (1.0 - visible) / (1.0 + params.misc.z * dot(d, d));
+        }
+    }
+    return background_albedo(p) * max(rgb, linear(params.ambient.rgb));
+}
+
+// Index of the first circle containing p, or -1
+fn circle_at(p: vec2<f32>) -> i32 {
+    for (var i = 0u; i < params.counts.y; i++) {
+        if distance(p, circles[i].pos.xy) <= circles[i].pos.z {
+            return i32(i);
+        }
+    }
+    return -1;
+}
+
+// See `World::shade_floor`
+fn shade(p: vec2<f32>) -> vec3<f32> {
+    var rgb = shade_scene(p);
+    if !has_flag(FLAG_FLOOR) || diagnostic() {
+        return rgb;
+    }
+    let floor_y = params.floor.x;
+    let half = params.camera.z * 0.5;
+    if p.y < floor_y - half || circle_at(p) >= 0 || inside_other_occluder(p) {
+        return rgb;
+    }
+    for (var i = 0u; i < params.counts.x; i++) {
+        if distance(p, lights[i].pos.xy) <= lights[i].pos.z {
+            return rgb;
+        }
+    }
+    if p.y <= floor_y + half {
+        return linear(params.fill.rgb);
+    }
+
+    let depth = p.y - floor_y;
+    let mirrored = vec2<f32>(p.x, floor_y - depth);
+    let hit = circle_at(mirrored);
+    if hit >= 0 {
+        let k = params.floor.y * max(1.0 - depth / (2.0 * circles[hit].pos.z), 0.0);
+        rgb = mix(rgb, shade_scene(mirrored), k);
+    }
+
+    var contact = 0.0;
+    for (var i = 0u; i < params.counts.y; i++) {
+        let c = circles[i].pos;
+        if c.y < floor_y {
+            let near = max(1.0 - max(floor_y - c.y - c.z, 0.0) / c.z, 0.0);
+            let across = (p.x - c.x) / c.z;
+            contact = max(contact, near * exp(-4.0 * across * across) * max(1.0 - depth / (CONTACT_DEPTH * c.z), 0.0));
+        }
+    }
+    return rgb * (1.0 - CONTACT_STRENGTH * contact);
+}
+
+// See `World::trace_ray`. WGSL can't recurse, so each reflection moves `p` to where the mirrored
+// ray lands and goes round again, with `weight` the share of the pixel still left to fill
+fn shade_scene(p0: vec2<f32>) -> vec3<f32> {
+    // See `View::Penumbra`
+    if has_flag(FLAG_PENUMBRA) {
+        var visible = vec3<f32>(0.0);
+        for (var i = 0u; i < params.counts.x; i++) {
+            visible += visibility(lights[i], p0);
+        }
+        return visible / f32(max(params.counts.x, 1u));
+    }
+    // See `View::Normals`
+    if has_flag(FLAG_NORMALS) {
+        var normal = vec3<f32>(0.0, 0.0, 1.0);
+        for (var i = 0u; i < params.counts.y; i++) {
+            if distance(p0, circles[i].pos.xy) <= circles[i].pos.z {
+                normal = sphere_normal(circles[i].pos.xyz, p0);
+                break;
+            }
+        }
+        return normal * 0.5 + 0.5;
+    }
+
+    var p = p0;
+    var weight = vec3<f32>(1.0);
+    var rgb = vec3<f32>(0.0);
+    for (var bounce = 0u; bounce <= params.bounces.x; bounce++) {
+        for (var i = 0u; i < params.counts.x; i++) {
+            if distance(p, lights[i].pos.xy) <= lights[i].pos.z {
+                var glow = vec3<f32>(1.0);
+                if has_flag(FLAG_TINT_LIGHTS) {
+                    glow = lights[i].color.rgb;
+                }
+                return rgb + weight * shade_sphere(vec4<f32>(lights[i].pos.xyz, 0.0), vec3<f32>(0.0), glow, p);
+            }
+        }
+
+        var hit = -1;
+        for (var i = 0u; i < params.counts.y; i++) {
+            if distance(p, circles[i].pos.xy) <= circles[i].pos.z {
+                hit = i32(i);
+                break;
+            }
+        }
+        if hit < 0 {
+            if inside_other_occluder(p) {
+                return rgb + weight * linear(params.fill.rgb);
+            }
+            return rgb + weight * shade_background(p);
+        }
+
+        // A translucent circle lets the background through, see `World::shade_with`
+        let c = circles[hit];
+        if c.emissive.w < 1.0 {
+            rgb += weight * (1.0 - c.emissive.w) * shade_background(p);
+            weight *= c.emissive.w;
+        }
+
+        // See `World::reflect`
+        let surface = shade_sphere(c.pos, c.albedo.rgb, c.emissive.rgb, p);
+        let k = c.albedo.w;
+        if k <= 0.0 || bounce >= params.bounces.x {
+            return rgb + weight * surface;
+        }
+        rgb += weight * (1.0 - k) * surface;
+        weight *= k;
+        let normal = sphere_normal(c.pos.xyz, p);
+        let n = normal.xy;
+        let nz = normal.z;
+        let rz = 2.0 * nz * nz - 1.0;
+        if rz >= 0.0 {
+            return rgb + weight * linear(params.ambient.rgb);
+        }
+        p += (nz * c.pos.z / -rz) * 2.0 * nz * n;
+    }
+    // Not reached: the last bounce always returns above
+    return rgb;
+}
+
+fn to_world(p: vec2<f32>) -> vec2<f32> {
+    return params.camera.xy + p * params.camera.z;
+}
+
+// See `World::rim_at`. A zero radius means no edge is that close
+fn rim_at(p: vec2<f32>) -> vec3<f32> {
+    let half = params.camera.z * 0.5;
+    for (var i = 0u; i < params.counts.x; i++) {
+        let d = distance(p, lights[i].pos.xy);
+        if abs(d - lights[i].pos.z) < half && d > 0.0 {
+            return lights[i].pos.xyz;
+        }
+    }
+    for (var i = 0u; i < params.counts.y; i++) {
+        let d = distance(p, circles[i].pos.xy);
+        if abs(d - circles[i].pos.z) < half && d > 0.0 {
+            return circles[i].pos.xyz;
+        }
+    }
+    return vec3<f32>(0.0);
+}
+
+// See `World::shade_rim`
+fn shade_rim(rim: vec3<f32>, p: vec2<f32>) -> vec3<f32> {
+    let scale = params.camera.z;
+    let d = distance(p, rim.xy);
+    let dir = (p - rim.xy) / d;
+    let inside = shade(rim.xy + dir * min(d, rim.z - RIM_INSET * scale));
+    let outside = shade(rim.xy + dir * max(d, rim.z + RIM_INSET * scale));
+    let t = clamp((rim.z - d) / scale + 0.5, 0.0, 1.0);
+    return mix(outside, inside, t * t * (3.0 - 2.0 * t));
+}
+
+// See `World::shade_pixel`
+fn shade_pixel(p: vec2<f32>) -> vec3<f32> {
+    let n = max(params.options.z, 1u);
+    if n == 1u {
+        let w = to_world(p);
+        if has_flag(FLAG_EDGE_AA) {
+            let rim = rim_at(w);
+            if rim.z > 0.0 {
+                return shade_rim(rim, w);
+            }
+        }
+        return shade(w);
+    }
+    var sum = vec3<f32>(0.0);
+    for (var j = 0u; j < n; j++) {
+        for (var i = 0u; i < n; i++) {
+            let offset = (vec2<f32>(f32(i), f32(j)) + 0.5) / f32(n) - 0.5;
+            sum += shade(to_world(p + offset));
+        }
+    }
+    return sum / f32(n * n);
+}
+
+// See `Vignette::factor`
+fn vignette(p: vec2<f32>) -> f32 {
+    let half = params.misc.xy / 2.0;
+    let d = length(p + 0.5 - half) / length(half);
+    let radius = params.vignette.y;
+    let t = clamp((d - radius) / max(1.0 - radius, 1.1920929e-7), 0.0, 1.0);
+    return 1.0 - params.vignette.x * t * t * (3.0 - 2.0 * t);
+}
+
+@fragment
+fn fs_main(@builtin(position) frag: vec4<f32>) -> @location(0) vec4<f32> {
+    // Every surface pixel inside a scaled-up scene pixel shades that scene pixel, like the
+    // nearest-neighbor upscale of the CPU frame
+    let p = floor((frag.xy - params.clip.xy) / params.misc.w);
+
+    var rgb = shade_pixel(p);
+    if has_flag(FLAG_TONEMAP) && !diagnostic() {
+        rgb = aces(rgb);
+    }
+    rgb = clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    if has_flag(FLAG_GAMMA) && !diagnostic() {
+        rgb = srgb_encode(rgb);
+    }
+    if has_flag(FLAG_VIGNETTE) && !diagnostic() {
+        rgb *= vignette(p);
+    }
+    // Quantize like `color::to_rgba8`, or `color::to_rgba8_dithered`
+    var offset = 0.0;
+    if has_flag(FLAG_DITHER) && !diagnostic() {
+        let q = vec2<u32>(p) % 4u;
+        offset = (f32(BAYER4[q.y * 4u + q.x]) + 0.5) / 16.0 - 0.5;
+    }
+    rgb = clamp(round(rgb * 255.0 + offset), vec3<f32>(0.0), vec3<f32>(255.0)) / 255.0;
+    // See `World::post_process`
+    if has_flag(FLAG_INVERT) {
+        rgb = 1.0 - rgb;
+    }
+
+    var o = textureLoad(overlay, vec2<i32>(p), 0);
+    if has_flag(FLAG_SRGB_OVERLAY) {
+        o = vec4<f32>(srgb_encode(o.rgb), o.a);
+    }
+    rgb = o.rgb + rgb * (1.0 - o.a);
+
+    // The surface re-encodes what we write when it's sRGB, so hand it linear values
+    if has_flag(FLAG_SRGB_TARGET) {
+        rgb = srgb_decode(rgb);
+    }
+    return vec4<f32>(rgb, 1.0);
+}
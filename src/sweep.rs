@@ -0,0 +1,111 @@
+//! Stepping one scene parameter across a range, for rendering a frame at each value.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::World;
+
+/// What a `Sweep` changes, always of the scene's first light.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Param {
+    LightX,
+    LightY,
+    LightRadius,
+}
+
+impl Param {
+    /// Name on the command line and in output file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::LightX => "light-x",
+            Param::LightY => "light-y",
+            Param::LightRadius => "light-r",
+        }
+    }
+}
+
+/// `steps` evenly spaced values of `param` from `from` to `to`, both ends included, written
+/// `<param>:<from>:<to>:<steps>`, e.g. `light-x:100:1180:12`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sweep {
+    pub param: Param,
+    pub from: f32,
+    pub to: f32,
+    pub steps: u32,
+}
+
+impl Sweep {
+    /// Every value in order. A single step only takes `from`.
+    pub fn values(&self) -> impl Iterator<Item = f32> + use<> {
+        let Sweep { from, to, steps, .. } = *self;
+        (0..steps).map(move |i| if steps > 1 { from + (to - from) * i as f32 / (steps - 1) as f32 } else { from })
+    }
+
+    /// Set the parameter to `value` in `world`, leaving it alone if there's no light to change.
+    pub fn apply(&self, world: &mut World, value: f32) {
+        let Some(light) = world.lights.get_mut(0) else {
+            return;
+        };
+        match self.param {
+            Param::LightX => light.x = value,
+            Param::LightY => light.y = value,
+            Param::LightRadius => light.r = value,
+        }
+        world.dirty = true;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSweepError(String);
+
+impl fmt::Display for ParseSweepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseSweepError {}
+
+impl FromStr for Sweep {
+    type Err = ParseSweepError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = |msg: &str| ParseSweepError(format!("{msg}, expected <param>:<from>:<to>:<steps> like light-x:100:1180:12"));
+        let [param, from, to, steps] = s.split(':').collect::<Vec<_>>()[..] else {
+            return Err(err("wrong number of fields"));
+        };
+        let param = [Param::LightX, Param::LightY, Param::LightRadius]
+            .into_iter()
+            .find(|p| p.name() == param)
+            .ok_or_else(|| err(&format!("unknown parameter {param:?}, not light-x, light-y or light-r")))?;
+        let number = |s: &str| s.parse::<f32>().ok().filter(|f| f.is_finite()).ok_or_else(|| err(&format!("{s:?} is not a number")));
+        let (from, to) = (number(from)?, number(to)?);
+        let steps = steps.parse().ok().filter(|&n| n > 0).ok_or_else(|| err(&format!("{steps:?} is not a positive step count")))?;
+        if param == Param::LightRadius && from.min(to) <= 0.0 {
+            return Err(err("a light's radius must stay positive"));
+        }
+        Ok(Sweep { param, from, to, steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scene;
+
+    #[test]
+    fn sweeps_parse_and_step_end_to_end() {
+        let sweep: Sweep = "light-x:100:400:4".parse().unwrap();
+        assert_eq!(sweep, Sweep { param: Param::LightX, from: 100.0, to: 400.0, steps: 4 });
+        assert_eq!(sweep.values().collect::<Vec<_>>(), [100.0, 200.0, 300.0, 400.0]);
+        assert_eq!("light-r:5:5:1".parse::<Sweep>().unwrap().values().collect::<Vec<_>>(), [5.0]);
+
+        for bad in ["light-x:1:2", "light-z:1:2:3", "light-y:a:2:3", "light-y:1:2:0", "light-r:0:10:2"] {
+            assert!(bad.parse::<Sweep>().is_err(), "{bad}");
+        }
+
+        let mut world = World::new(Scene::default());
+        sweep.apply(&mut world, 250.0);
+        assert_eq!(world.lights[0].x, 250.0);
+    }
+}
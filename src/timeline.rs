@@ -0,0 +1,80 @@
+//! Scripted motion: positions that lights and circles pass through at given times.
+
+use serde::{Deserialize, Serialize};
+
+/// Where some of the lights and circles are `time` seconds into the animation.
+///
+/// `lights[i]` places the scene's light `i`, and likewise for `circles`; either list may stop
+/// short, or be left out, to leave the rest alone at this keyframe. Between the keyframes that
+/// place it, a light or circle moves in a straight line at constant speed, and before the first
+/// and after the last it stays put. Keyframed circles ignore the physics.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub circles: Vec<[f32; 2]>,
+}
+
+/// Which list of positions in a `Keyframe`.
+#[derive(Clone, Copy)]
+pub enum Track {
+    Light(usize),
+    Circle(usize),
+}
+
+impl Track {
+    fn at(self, keyframe: &Keyframe) -> Option<[f32; 2]> {
+        match self {
+            Track::Light(i) => keyframe.lights.get(i).copied(),
+            Track::Circle(i) => keyframe.circles.get(i).copied(),
+        }
+    }
+}
+
+/// Whether any of `keyframes`, which are in time order, places `track`.
+pub fn animates(keyframes: &[Keyframe], track: Track) -> bool {
+    keyframes.iter().any(|k| track.at(k).is_some())
+}
+
+/// Where the `keyframes`, in time order, put `track` at `time`, or `None` if none of them
+/// place it.
+pub fn position(keyframes: &[Keyframe], track: Track, time: f32) -> Option<(f32, f32)> {
+    let mut placed = keyframes.iter().filter_map(|k| Some((k.time, track.at(k)?)));
+    let (mut t0, mut p0) = placed.next()?;
+    if time <= t0 {
+        return Some((p0[0], p0[1]));
+    }
+    for (t1, p1) in placed {
+        if time <= t1 {
+            let s = if t1 > t0 { (time - t0) / (t1 - t0) } else { 1.0 };
+            return Some((p0[0] + (p1[0] - p0[0]) * s, p0[1] + (p1[1] - p0[1]) * s));
+        }
+        (t0, p0) = (t1, p1);
+    }
+    Some((p0[0], p0[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_run_straight_between_the_keyframes_that_set_them() {
+        let keyframes = [
+            Keyframe { time: 0.0, lights: vec![[0.0, 0.0]], circles: vec![[50.0, 50.0]] },
+            Keyframe { time: 1.0, lights: vec![[100.0, 0.0]], circles: vec![] },
+            Keyframe { time: 3.0, lights: vec![[100.0, 200.0]], circles: vec![[250.0, 50.0]] },
+        ];
+        let light = |t| position(&keyframes, Track::Light(0), t);
+        assert_eq!(light(-1.0), Some((0.0, 0.0)));
+        assert_eq!(light(0.5), Some((50.0, 0.0)));
+        assert_eq!(light(2.0), Some((100.0, 100.0)));
+        assert_eq!(light(10.0), Some((100.0, 200.0)));
+        // The middle keyframe leaves the circle out, so it goes straight from the first to the last
+        assert_eq!(position(&keyframes, Track::Circle(0), 1.5), Some((150.0, 50.0)));
+        assert_eq!(position(&keyframes, Track::Light(1), 1.0), None);
+        assert!(!animates(&keyframes, Track::Circle(1)));
+    }
+}
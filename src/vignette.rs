@@ -0,0 +1,65 @@
+//! Darkening toward the corners of a finished frame.
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// How dark the corners get by default, as the share of their color taken away.
+pub const STRENGTH: f32 = 0.5;
+/// Where the darkening starts by default, as a share of the way from the center to a corner.
+pub const RADIUS: f32 = 0.5;
+
+/// Scales each pixel by `factor`, which falls from 1 at `radius` to `1 - strength` at the corners.
+#[derive(Clone, Copy, Serialize)]
+pub struct Vignette {
+    pub strength: f32,
+    pub radius: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self { strength: STRENGTH, radius: RADIUS }
+    }
+}
+
+impl Vignette {
+    /// How much of its color the pixel at (x, y) of a `width`×`height` frame keeps: a smoothstep
+    /// over its distance from the center, measured at the pixel's center in half diagonals.
+    pub fn factor(&self, x: u32, y: u32, width: u32, height: u32) -> f32 {
+        let (half_w, half_h) = (width as f32 / 2.0, height as f32 / 2.0);
+        let d = (x as f32 + 0.5 - half_w).hypot(y as f32 + 0.5 - half_h) / half_w.hypot(half_h);
+        let t = ((d - self.radius) / (1.0 - self.radius).max(f32::EPSILON)).clamp(0.0, 1.0);
+        1.0 - self.strength * t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Darken an RGBA `frame` of the given `width`, a row at a time in parallel.
+    pub fn apply(&self, frame: &mut [u8], width: u32) {
+        if width == 0 {
+            return;
+        }
+        let height = (frame.len() / 4) as u32 / width;
+        frame.par_chunks_exact_mut(4 * width as usize).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let k = self.factor(x as u32, y as u32, width, height);
+                for c in &mut pixel[..3] {
+                    *c = (*c as f32 * k).round() as u8;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_outside_of_the_radius_darkens() {
+        let vignette = Vignette { strength: 0.6, radius: 0.5 };
+        assert_eq!(vignette.factor(50, 25, 100, 50), 1.0);
+        assert_eq!(vignette.factor(70, 30, 100, 50), 1.0);
+        let corner = vignette.factor(0, 0, 100, 50);
+        assert!((corner - 0.4).abs() < 0.01, "{corner}");
+        let between = vignette.factor(10, 5, 100, 50);
+        assert!(corner < between && between < 1.0, "{between}");
+    }
+}
@@ -0,0 +1,1865 @@
+#[cfg(feature = "image")]
+use image::RgbaImage;
+use serde::Serialize;
+use rayon::prelude::*;
+use wide::f32x8;
+
+use crate::bloom::Bloom;
+use crate::camera::Camera;
+use crate::color::{self, Rgb, BLACK, WHITE};
+use crate::grid::Grid;
+use crate::light::Light;
+use crate::material::Material;
+use crate::occluder::{Circle, Occluder, Segment, LANES};
+use crate::orbit::Orbit;
+use crate::timeline::{self, Track};
+use crate::vignette::Vignette;
+use crate::overlay;
+use crate::scene::{Bounds, Scene};
+
+#[cfg(feature = "gui")]
+mod input;
+
+/// How quickly light dims with distance: brightness is `1 / (1 + falloff * d²)`. Zero disables falloff.
+const LIGHT_FALLOFF: f32 = 1.0e-5;
+
+/// Edge length in pixels of the square tiles `World::draw` renders in parallel.
+const TILE: usize = 64;
+
+/// Cell size of the occluder grid, and the number of shapes from which it pays for itself. Below
+/// that every ray simply tests every shape, which is also what lets `draw_lanes` batch them.
+const GRID_CELL: f32 = 64.0;
+const GRID_MIN_OCCLUDERS: usize = 32;
+
+/// Spacing in screen pixels of the pixels `draw_rays` traces to, and the colors of their rays.
+const RAY_SPACING: u32 = 40;
+const RAY_LIT: [u8; 3] = [0x20, 0xe0, 0x40];
+const RAY_BLOCKED: [u8; 3] = [0xe0, 0x30, 0x20];
+
+/// Spacing of the snapping grid in world units, unless set with `set_snap_size`.
+const SNAP_SIZE: f32 = 20.0;
+/// Color and opacity of the snapping grid's lines, and the closest they may be drawn on screen.
+const SNAP_GRID_COLOR: [u8; 3] = [0xff; 3];
+const SNAP_GRID_ALPHA: u32 = 40;
+const SNAP_GRID_MIN_SPACING: f32 = 4.0;
+
+/// How far inside and outside an edge `shade_rim` shades either side, in screen pixels.
+const RIM_INSET: f32 = 1e-3;
+
+/// Font scale of the cursor readout, and its gap from the frame's corner in screen pixels.
+const READOUT_SCALE: u32 = 2;
+const READOUT_MARGIN: u32 = 8;
+
+/// Bounce speed under gravity below which a circle stops, in pixels per second.
+const SETTLE_SPEED: f32 = 20.0;
+
+/// Shadow rays traced per light per pixel. One treats lights as points with hard shadows; more
+/// sample the light disk for a soft penumbra, at a cost that grows linearly with the count.
+const SHADOW_SAMPLES: u32 = 1;
+
+/// Supersampling factor: each pixel averages an `aa`×`aa` grid of sub-samples, so the cost grows
+/// with `aa²`. One samples each pixel once, with no antialiasing.
+const AA: u32 = 1;
+
+/// How fast an orbiting light goes round unless told otherwise, in degrees per second.
+const ORBIT_SPEED: f32 = 45.0;
+
+/// Sets of shadow samples `World::draw_progressive` averages before the frame stops changing.
+const MAX_PASSES: u32 = 64;
+
+/// How far below the floor a circle's contact shadow reaches, and how much of the light it
+/// takes away right under a circle touching the floor. The depth is a share of the circle's
+/// radius.
+const CONTACT_DEPTH: f32 = 0.25;
+const CONTACT_STRENGTH: f32 = 0.6;
+
+/// Share of the last frame each new one keeps with trails on, unless told otherwise.
+const TRAIL_DECAY: f32 = 0.85;
+
+/// What each pixel of the frame shows.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum View {
+    /// The lit scene.
+    Shaded,
+    /// The fraction of the lights' disks visible from each pixel, averaged over the lights, as a
+    /// grey level: black in full shadow, white in full light, and the penumbra in between. Light
+    /// filtered through colored glass shows in the glass's color.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code, reason = "only the H key switches to it"))]
+    Penumbra,
+    /// The surface normal of the circles, as used by `shade_sphere`, mapped from -1..1 to 0..1
+    /// per axis: x to red, y (down) to green and the height to blue. Everything else faces
+    /// straight up, a flat lavender.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code, reason = "only the N key switches to it"))]
+    Normals,
+}
+
+/// Unit normal at the point above (x, y) on the upper half of the sphere of radius `r` centered
+/// on the plane at (cx, cy), see `World::shade_sphere`. Past the rim it lies flat.
+#[inline]
+fn sphere_normal(cx: f32, cy: f32, r: f32, x: f32, y: f32) -> [f32; 3] {
+    let (nx, ny) = ((x - cx) / r, (y - cy) / r);
+    [nx, ny, (1.0 - nx * nx - ny * ny).max(0.0).sqrt()]
+}
+
+/// A `World`'s render settings at one moment, see `World::render_settings`.
+#[derive(Serialize)]
+pub struct RenderSettings {
+    /// Frame size in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Seconds of simulated time, which set the flicker.
+    pub time: f32,
+    /// See `Camera`.
+    pub camera_offset: (f32, f32),
+    pub zoom: f32,
+    pub shadow_samples: u32,
+    pub aa: u32,
+    pub falloff: f32,
+    pub gamma_correct: bool,
+    pub tonemap: bool,
+    pub dither: bool,
+    pub edge_aa: bool,
+    /// Only there while bloom is on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bloom: Option<Bloom>,
+    /// Only there while the vignette is on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vignette: Option<Vignette>,
+    pub invert: bool,
+    pub tint_light_disks: bool,
+    /// "shaded", or "penumbra" or "normals" for the H and N keys' views.
+    view: View,
+    /// Whether a background picture was painted in place of the gradient. The picture itself
+    /// isn't recorded.
+    pub background_image: bool,
+}
+
+/// The live scene: lights, moving circles and static shapes, plus the render settings.
+pub struct World {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Index into `lights` of the light being dragged, if any.
+    dragging: Option<usize>,
+    /// Index into `circles` of the circle being dragged, if any. It stops bouncing while held.
+    dragging_circle: Option<usize>,
+    /// World position of the mouse as of the last update, if it's over the window.
+    pointer: Option<(f32, f32)>,
+    /// Show the mouse's position and what's under it, see `draw_cursor_readout`.
+    pub(crate) show_cursor: bool,
+    /// Share of the way to the mouse a dragged light or circle moves per `STEP_DT`; 1 keeps it
+    /// under the mouse.
+    drag_smoothing: f32,
+    /// Round dragged positions to the nearest multiple of `snap_size`, and show that grid.
+    pub(crate) snap: bool,
+    snap_size: f32,
+    pub(crate) lights: Vec<Light>,
+    /// Each light's intensity this frame, see `Light::intensity_at`.
+    pub(crate) light_levels: Vec<f32>,
+    /// The light going round on its own, if any.
+    orbit: Option<Orbit>,
+    /// Radius and speed new orbits take, see `set_orbit_motion`.
+    orbit_radius: Option<f32>,
+    orbit_speed: f32,
+    /// Seconds of simulated time so far, which drives the flicker.
+    time: f32,
+    /// Draw each light's disk in its own color instead of white.
+    pub(crate) tint_light_disks: bool,
+    /// Distance attenuation factor, see `LIGHT_FALLOFF`.
+    pub(crate) falloff: f32,
+    pub(crate) ambient: [u8; 3],
+    /// Color of the non-circle occluders, see `Scene::fill`.
+    pub(crate) fill_color: [u8; 3],
+    /// Top and bottom background colors, see `Scene::gradient`.
+    pub(crate) gradient: Option<[[u8; 3]; 2]>,
+    /// Picture stretched over the scene's extent as the background, taking the gradient's place.
+    #[cfg(feature = "image")]
+    pub(crate) background: Option<RgbaImage>,
+    pub(crate) shadow_samples: u32,
+    pub(crate) aa: u32,
+    /// How many times a view ray may reflect off mirror circles, see `Scene::max_bounces`.
+    pub(crate) max_bounces: u32,
+    /// Blend in linear light and sRGB-encode the output, instead of blending raw bytes.
+    pub(crate) gamma_correct: bool,
+    /// Tonemap the lighting with `color::aces` instead of clipping it at 1.
+    pub(crate) tonemap: bool,
+    /// Quantize with `color::to_rgba8_dithered`, trading banding in smooth falloff for a fine
+    /// pattern.
+    pub(crate) dither: bool,
+    /// Blend the pixels on the edges of circles and light disks by how much of each they cover,
+    /// see `shade_rim`.
+    pub(crate) edge_aa: bool,
+    /// Make the brightest parts of each finished frame glow, see `Bloom`.
+    pub(crate) bloom: bool,
+    bloom_settings: Bloom,
+    /// Darken the finished frame toward its corners, see `Vignette`.
+    pub(crate) vignette: bool,
+    pub(crate) vignette_settings: Vignette,
+    /// Show the finished frame in negative, to make faint gradients and edges stand out.
+    pub(crate) invert: bool,
+    /// Fade each frame out of the last one instead of replacing it, see `blend_trail`.
+    pub(crate) trails: bool,
+    trail_decay: f32,
+    /// The last frame `blend_trail` left, empty while there's nothing to fade from.
+    trail: Vec<u8>,
+    pub(crate) view: View,
+    /// Where the frame looks into the world. Every position above is in world coordinates.
+    camera: Camera,
+    /// Circle physics, see `Scene::gravity` and `Scene::restitution`.
+    pub(crate) gravity: f32,
+    pub(crate) restitution: f32,
+    /// Where the circles bounce, or wrap around, see `Scene::bounds` and `Scene::wrap`.
+    bounds: Bounds,
+    wrap: bool,
+    pub(crate) circles: Vec<Circle>,
+    /// Whether any circle lets light through, which sends shadow rays down the slower path in
+    /// `transmittance`. Circles spawned at runtime are always opaque.
+    translucent: bool,
+    pub(crate) walls: Vec<Segment>,
+    occluders: Vec<Box<dyn Occluder>>,
+    /// Buckets every shape in `occluders()` order, rebuilt after each update. `None` for scenes
+    /// with fewer than `GRID_MIN_OCCLUDERS` shapes.
+    grid: Option<Grid>,
+    /// The scene this world was built from, which still describes the static shapes.
+    pub(crate) scene: Scene,
+    /// Freeze the circles in place; the lights can still be dragged.
+    #[cfg(feature = "gui")]
+    paused: bool,
+    /// Simulated seconds per second of real time while running, see `update`.
+    #[cfg(feature = "gui")]
+    time_scale: f32,
+    /// Lights added with the `J` key so far, which picks the next one's color.
+    #[cfg(feature = "gui")]
+    lights_added: usize,
+    /// Running sum of `passes` shaded frames, each with its own shadow samples, see
+    /// `draw_progressive`.
+    accum: Vec<Rgb>,
+    passes: u32,
+    /// Which set of shadow samples `visibility` takes, see `Light::sample_pass`. Only
+    /// `draw_progressive` moves it off 0, and only while it shades.
+    sample_pass: u32,
+    /// Set whenever the picture may have changed since the last `draw_progressive`, which then
+    /// starts over.
+    pub(crate) dirty: bool,
+}
+
+impl World {
+    pub fn new(scene: Scene) -> Self {
+        let occluders = scene.rects.iter()
+            .map(|r| Box::new(r.clone()) as Box<dyn Occluder>)
+            .chain(scene.polygons.iter().map(|p| Box::new(p.clone()) as Box<dyn Occluder>))
+            .chain(scene.sdfs.iter().map(|s| Box::new(s.clone()) as Box<dyn Occluder>))
+            .collect();
+        let mut world = Self {
+            width: scene.width,
+            height: scene.height,
+            dragging: None,
+            dragging_circle: None,
+            pointer: None,
+            show_cursor: false,
+            drag_smoothing: 1.0,
+            snap: false,
+            snap_size: SNAP_SIZE,
+            lights: scene.lights.clone(),
+            light_levels: Vec::new(),
+            orbit: None,
+            orbit_radius: None,
+            orbit_speed: ORBIT_SPEED,
+            time: 0.0,
+            tint_light_disks: false,
+            falloff: LIGHT_FALLOFF,
+            ambient: scene.ambient,
+            fill_color: scene.fill,
+            gradient: scene.gradient,
+            #[cfg(feature = "image")]
+            background: None,
+            shadow_samples: SHADOW_SAMPLES,
+            aa: AA,
+            max_bounces: scene.max_bounces,
+            gamma_correct: false,
+            tonemap: false,
+            dither: false,
+            edge_aa: false,
+            bloom: false,
+            bloom_settings: Bloom::default(),
+            vignette: false,
+            vignette_settings: Vignette::default(),
+            invert: false,
+            trails: false,
+            trail_decay: TRAIL_DECAY,
+            trail: Vec::new(),
+            view: View::Shaded,
+            camera: Camera::default(),
+            gravity: scene.gravity,
+            restitution: scene.restitution,
+            bounds: scene.play_area(),
+            wrap: scene.wrap,
+            circles: scene.circles.clone(),
+            translucent: scene.circles.iter().any(|c| c.opacity < 1.0),
+            walls: scene.walls.clone(),
+            occluders,
+            grid: None,
+            scene,
+            #[cfg(feature = "gui")]
+            paused: false,
+            #[cfg(feature = "gui")]
+            time_scale: 1.0,
+            #[cfg(feature = "gui")]
+            lights_added: 0,
+            accum: Vec::new(),
+            passes: 0,
+            sample_pass: 0,
+            dirty: true,
+        };
+        world.apply_keyframes();
+        world.update_light_levels();
+        world.rebuild_grid();
+        world
+    }
+
+    /// Put every light and circle back where the scene placed them, with their starting speeds,
+    /// and center the view again. Render settings are left as they are.
+    pub fn reset(&mut self) {
+        self.dragging = None;
+        self.dragging_circle = None;
+        self.orbit = None;
+        self.lights = self.scene.lights.clone();
+        self.time = 0.0;
+        self.camera = Camera::default();
+        self.circles = self.scene.circles.clone();
+        self.walls = self.scene.walls.clone();
+        self.trail.clear();
+        self.apply_keyframes();
+        self.update_light_levels();
+        self.rebuild_grid();
+        self.dirty = true;
+    }
+
+    /// Start over from `scene` in place of the one this world was built from, as `reset` does,
+    /// keeping the frame size, the view and every render setting.
+    pub fn load_scene(&mut self, scene: Scene) {
+        let fresh = World::new(scene);
+        self.ambient = fresh.ambient;
+        self.fill_color = fresh.fill_color;
+        self.gradient = fresh.gradient;
+        self.max_bounces = fresh.max_bounces;
+        self.gravity = fresh.gravity;
+        self.restitution = fresh.restitution;
+        self.bounds = fresh.bounds;
+        self.wrap = fresh.wrap;
+        self.translucent = fresh.translucent;
+        self.occluders = fresh.occluders;
+        self.scene = fresh.scene;
+        let camera = self.camera;
+        self.reset();
+        self.camera = camera;
+    }
+
+    /// Have dragged lights and circles ease toward the mouse, covering `smoothing` of the distance
+    /// left every 1/60 s whatever the frame rate, instead of jumping to it. 1 turns it off.
+    pub fn set_drag_smoothing(&mut self, smoothing: f32) {
+        self.drag_smoothing = smoothing.clamp(f32::MIN_POSITIVE, 1.0);
+    }
+
+    /// Turn bloom on or off, glowing with `settings` while on.
+    pub fn set_bloom(&mut self, on: bool, settings: Bloom) {
+        self.bloom = on;
+        self.bloom_settings = settings;
+        self.dirty = true;
+    }
+
+    /// Turn the vignette on or off, darkening by `settings` while on.
+    pub fn set_vignette(&mut self, on: bool, settings: Vignette) {
+        self.vignette = on;
+        self.vignette_settings = settings;
+        self.dirty = true;
+    }
+
+    /// Turn trails on or off, each frame keeping `decay` of the last one while on, see
+    /// `blend_trail`.
+    pub fn set_trails(&mut self, on: bool, decay: f32) {
+        self.trails = on;
+        self.trail_decay = decay.clamp(0.0, 1.0);
+    }
+
+    /// Effects that are on but only the CPU renderer draws, so the GPU's frame goes without them:
+    /// bloom, trails, and the refining of soft shadows over frames the scene holds still for.
+    pub fn cpu_only_effects(&self) -> Vec<&'static str> {
+        [(self.bloom, "bloom"), (self.trails, "trails"), (self.shadow_samples > 1, "shadow refinement")]
+            .into_iter()
+            .filter_map(|(on, name)| on.then_some(name))
+            .collect()
+    }
+
+    /// Space the snapping grid `size` world units apart.
+    pub fn set_snap_size(&mut self, size: f32) {
+        self.snap_size = size;
+    }
+
+    /// (x, y) moved to the nearest point of the snapping grid while snapping is on.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code, reason = "only dragging snaps"))]
+    fn snap_point(&self, x: f32, y: f32) -> (f32, f32) {
+        if !self.snap {
+            return (x, y);
+        }
+        let size = self.snap_size;
+        ((x / size).round() * size, (y / size).round() * size)
+    }
+
+    /// Have orbits started from now on go round at `radius` from the middle of the scene, or
+    /// at whatever distance the light is without one, at `speed` degrees per second.
+    pub fn set_orbit_motion(&mut self, radius: Option<f32>, speed: f32) {
+        self.orbit_radius = radius;
+        self.orbit_speed = speed;
+    }
+
+    /// Send `lights[light]` round the middle of the scene as set by `set_orbit_motion`, in place
+    /// of any other light already orbiting. It goes on until `stop_orbit`, `reset` or, in the
+    /// window, being dragged.
+    pub fn start_orbit(&mut self, light: usize) {
+        let Some(l) = self.lights.get(light) else {
+            return;
+        };
+        let (w, h) = self.world_size();
+        let orbit = Orbit::through(light, (l.x, l.y), (w / 2.0, h / 2.0), self.orbit_radius, self.orbit_speed);
+        (self.lights[light].x, self.lights[light].y) = orbit.position();
+        self.orbit = Some(orbit);
+        self.dirty = true;
+    }
+
+    /// Leave the orbiting light wherever it has got to.
+    pub fn stop_orbit(&mut self) {
+        self.orbit = None;
+    }
+
+    /// Add `light` after the others. Like a spawned circle, it's gone again after a `reset`.
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+        self.update_light_levels();
+        self.dirty = true;
+    }
+
+    /// Take `lights[i]` out, along with its keyframes and its orbit, and move the lights after
+    /// it down one. A light from the scene is taken out of the scene as well, so a `reset`
+    /// doesn't bring it back and a saved scene's keyframes still line up with its lights.
+    pub fn remove_light(&mut self, i: usize) {
+        if i >= self.lights.len() {
+            return;
+        }
+        self.lights.remove(i);
+        if i < self.scene.lights.len() {
+            self.scene.lights.remove(i);
+            for keyframe in &mut self.scene.keyframes {
+                if i < keyframe.lights.len() {
+                    keyframe.lights.remove(i);
+                }
+            }
+        }
+        // Whatever pointed past it now points one lower
+        let shift = |j: usize| (j != i).then(|| if j > i { j - 1 } else { j });
+        self.dragging = self.dragging.and_then(shift);
+        self.orbit = self.orbit.and_then(|o| Some(Orbit { light: shift(o.light)?, ..o }));
+        self.update_light_levels();
+        self.dirty = true;
+    }
+
+    /// Paint `image` behind the scene, stretched to cover its width and height. Lighting and
+    /// shadow then fall on the picture, as they would on the gradient.
+    #[cfg(feature = "image")]
+    pub fn set_background(&mut self, image: RgbaImage) {
+        self.background = Some(image);
+        self.dirty = true;
+    }
+
+    /// Render frames of `width`×`height` pixels from now on. The scene keeps its own size and is
+    /// scaled to fit, see `Camera`.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.rebuild_grid();
+        self.dirty = true;
+    }
+
+    /// Everything besides the scene that decides how the next frame comes out.
+    pub fn render_settings(&self) -> RenderSettings {
+        RenderSettings {
+            width: self.width,
+            height: self.height,
+            time: self.time,
+            camera_offset: self.camera.offset,
+            zoom: self.camera.zoom,
+            shadow_samples: self.shadow_samples,
+            aa: self.aa,
+            falloff: self.falloff,
+            gamma_correct: self.gamma_correct,
+            tonemap: self.tonemap,
+            dither: self.dither,
+            edge_aa: self.edge_aa,
+            bloom: self.bloom.then_some(self.bloom_settings),
+            vignette: self.vignette.then_some(self.vignette_settings),
+            invert: self.invert,
+            tint_light_disks: self.tint_light_disks,
+            view: self.view,
+            background_image: self.has_background_image(),
+        }
+    }
+
+    /// Snapshot of the current state, including dragged lights and moving circles.
+    pub fn to_scene(&self) -> Scene {
+        Scene {
+            ambient: self.ambient,
+            fill: self.fill_color,
+            gradient: self.gradient,
+            max_bounces: self.max_bounces,
+            gravity: self.gravity,
+            restitution: self.restitution,
+            lights: self.lights.clone(),
+            circles: self.circles.clone(),
+            walls: self.walls.clone(),
+            ..self.scene.clone()
+        }
+    }
+
+    /// Advance the physics and any orbiting light by `dt` seconds, whether or not the world is
+    /// paused, and re-bucket the shapes for the next `draw`.
+    ///
+    /// `update` calls this with the time since its last call; without a window, call it directly
+    /// between frames.
+    pub fn step(&mut self, dt: f32) {
+        // Move the circles, each bouncing off the edges of the play area on its own.
+        // Only flip when heading outwards so a circle dropped past an edge comes back.
+        let Bounds { left, top, right, bottom } = self.bounds;
+        for (i, circle) in self.circles.iter_mut().enumerate() {
+            if self.dragging_circle == Some(i) || timeline::animates(&self.scene.keyframes, Track::Circle(i)) {
+                continue;
+            }
+            circle.vy += self.gravity * dt;
+            circle.x += circle.vx * dt;
+            circle.y += circle.vy * dt;
+            if self.wrap {
+                // Only once all of a circle is past an edge does it come back in, from just
+                // outside the opposite one, so it never pops in or out in view
+                let r = circle.r;
+                let wrap = |v: f32, lo: f32, hi: f32| lo - r + (v - (lo - r)).rem_euclid(hi - lo + 2.0 * r);
+                circle.x = wrap(circle.x, left, right);
+                circle.y = wrap(circle.y, top, bottom);
+                continue;
+            }
+            if (circle.x < left + circle.r && circle.vx < 0.0) || (circle.x > right - circle.r && circle.vx > 0.0) {
+                circle.vx = -circle.vx * self.restitution;
+            }
+            if (circle.y < top + circle.r && circle.vy < 0.0)
+                || (circle.y > bottom - circle.r && circle.vy > 0.0)
+            {
+                circle.vy = -circle.vy * self.restitution;
+                // Under gravity, keep circles out of the bottom and let them come to rest once
+                // the bounces get too small to see, rather than buzzing on the spot
+                if self.gravity != 0.0 {
+                    circle.y = circle.y.max(top + circle.r).min(bottom - circle.r);
+                    if circle.vy.abs() < (2.0 * self.gravity.abs() * dt).max(SETTLE_SPEED) {
+                        circle.vy = 0.0;
+                    }
+                }
+            }
+        }
+        self.collide_circles();
+        if let Some(orbit) = &mut self.orbit {
+            orbit.advance(dt);
+            let light = &mut self.lights[orbit.light];
+            (light.x, light.y) = orbit.position();
+        }
+        self.time += dt;
+        self.apply_keyframes();
+        self.update_light_levels();
+        self.rebuild_grid();
+        self.dirty |= dt != 0.0;
+    }
+
+    /// Put the keyframed lights and circles where the scene's timeline has them now, except any
+    /// being dragged or orbiting.
+    fn apply_keyframes(&mut self) {
+        let keyframes = &self.scene.keyframes;
+        if keyframes.is_empty() {
+            return;
+        }
+        for (i, light) in self.lights.iter_mut().enumerate() {
+            if self.dragging != Some(i)
+                && self.orbit.is_none_or(|o| o.light != i)
+                && let Some(pos) = timeline::position(keyframes, Track::Light(i), self.time)
+            {
+                (light.x, light.y) = pos;
+            }
+        }
+        for (i, circle) in self.circles.iter_mut().enumerate() {
+            if self.dragging_circle != Some(i)
+                && let Some(pos) = timeline::position(keyframes, Track::Circle(i), self.time)
+            {
+                (circle.x, circle.y) = pos;
+            }
+        }
+    }
+
+    /// Every shape in the scene, the bouncing circles first and the walls after them.
+    fn occluders(&self) -> impl Iterator<Item = &dyn Occluder> {
+        self.circles.iter()
+            .map(|c| c as &dyn Occluder)
+            .chain(self.other_occluders())
+    }
+
+    /// Every shape except the circles, which `draw_lanes` tests eight pixels at a time.
+    fn other_occluders(&self) -> impl Iterator<Item = &dyn Occluder> {
+        self.walls.iter()
+            .map(|w| w as &dyn Occluder)
+            .chain(self.occluders.iter().map(|o| o.as_ref()))
+    }
+
+    /// The `i`th shape in `occluders()` order.
+    fn occluder(&self, i: usize) -> &dyn Occluder {
+        let first_wall = self.circles.len();
+        let first_other = first_wall + self.walls.len();
+        match i {
+            i if i < first_wall => &self.circles[i],
+            i if i < first_other => &self.walls[i - first_wall],
+            i => self.occluders[i - first_other].as_ref(),
+        }
+    }
+
+    /// Size of the frame being drawn, in pixels.
+    fn frame_size(&self) -> (f32, f32) {
+        (self.width as f32, self.height as f32)
+    }
+
+    /// Extent of the scene in world units, which the camera fits into the frame.
+    fn world_size(&self) -> (f32, f32) {
+        (self.scene.width as f32, self.scene.height as f32)
+    }
+
+    /// `Camera::transform` for the current frame and scene sizes.
+    pub(crate) fn view_transform(&self) -> ((f32, f32), f32) {
+        self.camera.transform(self.frame_size(), self.world_size())
+    }
+
+    /// World position of the screen point (sx, sy).
+    fn to_world(&self, sx: f32, sy: f32) -> (f32, f32) {
+        self.camera.to_world(sx, sy, self.frame_size(), self.world_size())
+    }
+
+    /// Bucket the shapes at their current positions, over an area covering the frame and every
+    /// light disk so that all shadow rays start and end inside the grid.
+    fn rebuild_grid(&mut self) {
+        let count = self.circles.len() + self.walls.len() + self.occluders.len();
+        self.grid = (count >= GRID_MIN_OCCLUDERS).then(|| {
+            let (x0, y0) = self.to_world(0.0, 0.0);
+            let (x1, y1) = self.to_world(self.width as f32, self.height as f32);
+            let (w, h) = self.world_size();
+            let area = self.lights.iter().fold(
+                [x0.min(0.0), y0.min(0.0), x1.max(w), y1.max(h)],
+                |[x0, y0, x1, y1], l| [x0.min(l.x - l.r), y0.min(l.y - l.r), x1.max(l.x + l.r), y1.max(l.y + l.r)],
+            );
+            let bounds: Vec<_> = self.occluders().map(|o| o.bounds()).collect();
+            Grid::new(GRID_CELL, area, &bounds)
+        });
+    }
+
+    /// Return true if any shape whose index in `occluders()` order passes `include` blocks the
+    /// segment from (lx, ly) to (x, y).
+    fn ray_blocked(&self, lx: f32, ly: f32, x: f32, y: f32, include: impl Fn(usize) -> bool) -> bool {
+        let hit = |i: usize, o: &dyn Occluder| include(i) && o.blocks_ray(lx, ly, x, y);
+        self.grid.as_ref()
+            .and_then(|g| g.any_along(lx, ly, x, y, |i| hit(i as usize, self.occluder(i as usize))))
+            .unwrap_or_else(|| self.occluders().enumerate().any(|(i, o)| hit(i, o)))
+    }
+
+    /// Share of each channel of the light leaving (lx, ly) that arrives at (x, y): none past an
+    /// opaque shape, else whatever every translucent circle in the way lets through, which is
+    /// `1 - opacity` of the light filtered by the circle's albedo. White glass dims the light,
+    /// colored glass tints it too, and crossing several panes stacks their colors.
+    fn transmittance(&self, lx: f32, ly: f32, x: f32, y: f32) -> Rgb {
+        if !self.translucent {
+            return if self.ray_blocked(lx, ly, x, y, |_| true) { BLACK } else { WHITE };
+        }
+        // Any opaque shape settles it, so only then go through the translucent circles
+        let opaque = |i: usize| i >= self.circles.len() || self.circles[i].opacity >= 1.0;
+        if self.ray_blocked(lx, ly, x, y, opaque) {
+            return BLACK;
+        }
+        self.circles.iter()
+            .filter(|c| c.opacity < 1.0 && c.blocks_ray(lx, ly, x, y))
+            .fold(WHITE, |through, c| {
+                let tint = self.linear(c.material.albedo);
+                [0, 1, 2].map(|i| through[i] * (1.0 - c.opacity) * tint[i])
+            })
+    }
+
+    /// Index in `occluders()` order of the first shape holding (x, y), if any.
+    fn occluder_at(&self, x: f32, y: f32) -> Option<usize> {
+        // Grid cells list their shapes in ascending order, so both ways find the same one
+        match self.grid.as_ref().and_then(|g| g.at(x, y)) {
+            Some(cell) => cell.iter().map(|&i| i as usize).find(|&i| self.occluder(i).contains(x, y)),
+            None => self.occluders().position(|o| o.contains(x, y)),
+        }
+    }
+
+    /// Share of each channel of `light` that reaches (x, y): the `transmittance` from each of its
+    /// shadow samples, averaged. A sample is blocked or not as a whole, so where several shapes
+    /// cover the same part of the light it's only taken away once.
+    fn visibility(&self, light: &Light, x: f32, y: f32) -> Rgb {
+        let n = self.shadow_samples.max(1);
+        let mut through = BLACK;
+        for i in 0..n {
+            let (sx, sy) = light.sample_pass(i, n, self.sample_pass);
+            for (t, c) in through.iter_mut().zip(self.transmittance(sx, sy, x, y)) {
+                *t += c;
+            }
+        }
+        through.map(|t| t / n as f32)
+    }
+
+    /// Convert a scene color to the space lights are blended in.
+    #[inline]
+    fn linear(&self, rgb: [u8; 3]) -> Rgb {
+        let c = color::from_u8(rgb);
+        if self.gamma_correct { c.map(color::srgb_decode) } else { c }
+    }
+
+    /// Pairs of circles that may be touching, each as (i, j) with i < j. For now that's every
+    /// pair, which is cheap enough at up to `MAX_CIRCLES`; a grid over the circles could narrow
+    /// it down without `collide_circles` changing.
+    fn circle_pairs(&self) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let n = self.circles.len();
+        (0..n).flat_map(move |i| (i + 1..n).map(move |j| (i, j)))
+    }
+
+    /// Resolve every pair of overlapping circles: push them apart along the line between their
+    /// centers so they don't stick, then exchange momentum along it if they're still closing.
+    ///
+    /// Masses go with area, so big circles shove small ones aside. A circle being dragged acts as
+    /// if it were infinitely heavy, and bounces lose speed by the same `restitution` as at the
+    /// edges, so the default of 1 is perfectly elastic.
+    fn collide_circles(&mut self) {
+        for (i, j) in self.circle_pairs() {
+            let (a, b) = (&self.circles[i], &self.circles[j]);
+            let (dx, dy) = (b.x - a.x, b.y - a.y);
+            let dist = (dx * dx + dy * dy).sqrt();
+            let overlap = a.r + b.r - dist;
+            if overlap <= 0.0 {
+                continue;
+            }
+            let inv_mass = |k: usize, r: f32| if self.dragging_circle == Some(k) { 0.0 } else { 1.0 / (r * r) };
+            let (inv_a, inv_b) = (inv_mass(i, a.r), inv_mass(j, b.r));
+            if inv_a + inv_b == 0.0 {
+                continue;
+            }
+            // Concentric circles have no line between them, so pick one
+            let (nx, ny) = if dist > 0.0 { (dx / dist, dy / dist) } else { (1.0, 0.0) };
+            let closing = (b.vx - a.vx) * nx + (b.vy - a.vy) * ny;
+            let impulse = if closing < 0.0 { -(1.0 + self.restitution) * closing / (inv_a + inv_b) } else { 0.0 };
+            let push = overlap / (inv_a + inv_b);
+
+            let a = &mut self.circles[i];
+            a.x -= push * inv_a * nx;
+            a.y -= push * inv_a * ny;
+            a.vx -= impulse * inv_a * nx;
+            a.vy -= impulse * inv_a * ny;
+            let b = &mut self.circles[j];
+            b.x += push * inv_b * nx;
+            b.y += push * inv_b * ny;
+            b.vx += impulse * inv_b * nx;
+            b.vy += impulse * inv_b * ny;
+        }
+    }
+
+    fn update_light_levels(&mut self) {
+        self.light_levels = self.lights.iter()
+            .enumerate()
+            .map(|(i, l)| l.intensity_at(self.time, i as f32))
+            .collect();
+    }
+
+    /// Color `lights[i]` shines with this frame.
+    fn light_color(&self, i: usize) -> Rgb {
+        self.linear(self.lights[i].color).map(|c| c * self.light_levels[i])
+    }
+
+    /// Color of the scene at (x, y).
+    fn shade(&self, x: f32, y: f32) -> Rgb {
+        match self.view {
+            View::Shaded => self.shade_floor(x, y),
+            View::Penumbra => {
+                let mut visible = BLACK;
+                for light in &self.lights {
+                    for (v, c) in visible.iter_mut().zip(self.visibility(light, x, y)) {
+                        *v += c;
+                    }
+                }
+                visible.map(|v| v / self.lights.len().max(1) as f32)
+            }
+            View::Normals => {
+                let n = match self.occluder_at(x, y) {
+                    Some(i) if i < self.circles.len() => {
+                        let c = &self.circles[i];
+                        sphere_normal(c.x, c.y, c.r, x, y)
+                    }
+                    _ => [0.0, 0.0, 1.0],
+                };
+                n.map(|n| n * 0.5 + 0.5)
+            }
+        }
+    }
+
+    /// `trace_ray` at (x, y), with the scene's `Floor` if it has one: the line itself in the fill
+    /// color and, on the open ground below it, the circles' reflections and contact shadows.
+    fn shade_floor(&self, x: f32, y: f32) -> Rgb {
+        let rgb = self.trace_ray(x, y, 0);
+        let Some(floor) = self.scene.floor else {
+            return rgb;
+        };
+        let half = self.view_transform().1 * 0.5;
+        if y < floor.y - half
+            || self.lights.iter().any(|l| l.contains(x, y))
+            || self.occluder_at(x, y).is_some()
+        {
+            return rgb;
+        }
+        if y <= floor.y + half {
+            return self.linear(self.fill_color);
+        }
+
+        // The circle mirrored onto here, fading with the distance from the floor
+        let depth = y - floor.y;
+        let mirrored = floor.y - depth;
+        let mut rgb = match self.occluder_at(x, mirrored) {
+            Some(i) if i < self.circles.len() => {
+                let k = floor.reflection * (1.0 - depth / (2.0 * self.circles[i].r)).max(0.0);
+                let reflected = self.trace_ray(x, mirrored, 0);
+                [0, 1, 2].map(|i| rgb[i] * (1.0 - k) + reflected[i] * k)
+            }
+            _ => rgb,
+        };
+
+        // Darkest under the middle of a circle resting on the floor, fading out sideways, with
+        // depth, and as the circle rises off the floor
+        let contact = self.circles.iter()
+            .filter(|c| c.y < floor.y)
+            .map(|c| {
+                let near = (1.0 - (floor.y - c.y - c.r).max(0.0) / c.r).max(0.0);
+                let across = (x - c.x) / c.r;
+                near * (-4.0 * across * across).exp() * (1.0 - depth / (CONTACT_DEPTH * c.r)).max(0.0)
+            })
+            .fold(0.0, f32::max);
+        for c in &mut rgb {
+            *c *= 1.0 - CONTACT_STRENGTH * contact;
+        }
+        rgb
+    }
+
+    /// Color seen along a view ray that lands on the plane at (x, y) after `bounce` reflections.
+    fn trace_ray(&self, x: f32, y: f32, bounce: u32) -> Rgb {
+        self.shade_with(x, y, bounce, |_, light| self.visibility(light, x, y))
+    }
+
+    /// `trace_ray`, with `visibility(i, light)` giving the share of each channel of `lights[i]`
+    /// that reaches (x, y).
+    fn shade_with(&self, x: f32, y: f32, bounce: u32, visibility: impl Fn(usize, &Light) -> Rgb) -> Rgb {
+        // Inside a light circle => glowing white, or in the light's color when tinting
+        if let Some(light) = self.lights.iter().find(|l| l.contains(x, y)) {
+            let glow = if self.tint_light_disks { light.color } else { [0xff; 3] };
+            return self.shade_sphere(light.x, light.y, light.r, &Material::emitting(glow), x, y);
+        }
+
+        // Inside a circle => diffuse shading, mixed with its reflection while bounces are left and
+        // with the background behind it if it's translucent, inside any other occluder => its fill
+        match self.occluder_at(x, y) {
+            Some(i) if i < self.circles.len() => {
+                let c = &self.circles[i];
+                let mut rgb = self.shade_sphere(c.x, c.y, c.r, &c.material, x, y);
+                let k = c.material.reflectivity;
+                if k > 0.0 && bounce < self.max_bounces {
+                    let reflected = self.reflect(c, x, y, bounce);
+                    rgb = [0, 1, 2].map(|i| rgb[i] * (1.0 - k) + reflected[i] * k);
+                }
+                if c.opacity < 1.0 {
+                    let behind = self.shade_background(x, y, &visibility);
+                    rgb = [0, 1, 2].map(|i| rgb[i] * c.opacity + behind[i] * (1.0 - c.opacity));
+                }
+                rgb
+            }
+            Some(_) => self.linear(self.fill_color),
+            None => self.shade_background(x, y, &visibility),
+        }
+    }
+
+    /// Add up every light that reaches the background at (x, y), dimmed with distance and by
+    /// `visibility`, never going darker than the ambient level, all filtered by the background's
+    /// color there.
+    ///
+    /// A shadow is only the absence of the light casting it: a light a shape hides adds its own
+    /// `Light::ambient` in place of its color, nothing by default, and the others add their
+    /// colors as usual. With a red and a blue light, the red one's shadow is lit blue, and only
+    /// where both are hidden does the scene's ambient color show.
+    fn shade_background(&self, x: f32, y: f32, visibility: &impl Fn(usize, &Light) -> Rgb) -> Rgb {
+        let albedo = self.background_albedo(x, y);
+        let mut rgb = BLACK;
+        for (i, light) in self.lights.iter().enumerate() {
+            // Outside a spotlight's beam there's no need to trace the shadow at all
+            if !light.shines_on(x, y) {
+                continue;
+            }
+            let visible = visibility(i, light);
+            let shadowed = light.ambient != [0; 3];
+            if visible == BLACK && !shadowed {
+                continue;
+            }
+            let dist2 = (x - light.x).powi(2) + (y - light.y).powi(2);
+            let attenuation = visible.map(|v| v / (1.0 + self.falloff * dist2));
+            for ((c, l), a) in rgb.iter_mut().zip(self.light_color(i)).zip(attenuation) {
+                *c += l * a;
+            }
+            if shadowed {
+                let hidden = visible.map(|v| (1.0 - v) / (1.0 + self.falloff * dist2));
+                for ((c, s), h) in rgb.iter_mut().zip(self.linear(light.ambient)).zip(hidden) {
+                    *c += s * h;
+                }
+            }
+        }
+        let ambient = self.linear(self.ambient);
+        [0, 1, 2].map(|i| albedo[i] * rgb[i].max(ambient[i]))
+    }
+
+    /// Whether `set_background` gave the scene a picture.
+    #[cfg(feature = "image")]
+    fn has_background_image(&self) -> bool {
+        self.background.is_some()
+    }
+
+    #[cfg(not(feature = "image"))]
+    fn has_background_image(&self) -> bool {
+        false
+    }
+
+    /// The background picture's pixel nearest to (x, y), if there is a picture.
+    #[cfg(feature = "image")]
+    fn background_pixel(&self, x: f32, y: f32) -> Option<[u8; 3]> {
+        let image = self.background.as_ref()?;
+        let (w, h) = self.world_size();
+        let texel = |v: f32, extent: f32, size: u32| ((v / extent * size as f32) as u32).min(size - 1);
+        let [r, g, b, _] = image.get_pixel(texel(x, w, image.width()), texel(y, h, image.height())).0;
+        Some([r, g, b])
+    }
+
+    #[cfg(not(feature = "image"))]
+    fn background_pixel(&self, _x: f32, _y: f32) -> Option<[u8; 3]> {
+        None
+    }
+
+    /// Color of the background at (x, y): the nearest pixel of the background image, else the
+    /// gradient, else white.
+    fn background_albedo(&self, x: f32, y: f32) -> Rgb {
+        if let Some(rgb) = self.background_pixel(x, y) {
+            return self.linear(rgb);
+        }
+        let Some([top, bottom]) = self.gradient else {
+            return WHITE;
+        };
+        let t = (y / self.scene.height as f32).clamp(0.0, 1.0);
+        let (top, bottom) = (self.linear(top), self.linear(bottom));
+        [0, 1, 2].map(|i| top[i] + (bottom[i] - top[i]) * t)
+    }
+
+    /// Color mirrored by the circle `c` at the point (x, y), after `bounce` earlier reflections.
+    ///
+    /// As in `shade_sphere`, the circle is the upper half of a sphere centered on the plane, seen
+    /// from straight above. The view ray (0, 0, -1) mirrors about the normal to
+    /// (2nz·nx, 2nz·ny, 2nz² - 1). Near the center that points back up into the empty sky, which
+    /// only gives the ambient level. Further out it points down and away from the center, and
+    /// lands on the plane past the circle's edge, having left the surface at height `nz * r`;
+    /// whatever is there is traced in turn.
+    fn reflect(&self, c: &Circle, x: f32, y: f32, bounce: u32) -> Rgb {
+        let [nx, ny, nz] = sphere_normal(c.x, c.y, c.r, x, y);
+        let rz = 2.0 * nz * nz - 1.0;
+        if rz >= 0.0 {
+            return self.linear(self.ambient);
+        }
+        let t = nz * c.r / -rz;
+        self.trace_ray(x + t * 2.0 * nz * nx, y + t * 2.0 * nz * ny, bounce + 1)
+    }
+
+    /// Blinn-Phong shading of the point (x, y) on the sphere of radius `r` at (cx, cy).
+    ///
+    /// The sphere is seen from above and lit by lights in its plane. The normal's in-plane part is
+    /// the direction from the center out through the point, scaled by how far out it is
+    /// (`(p - c) / r`), and its height is whatever makes it unit length. Each light adds its color
+    /// filtered by the albedo times the diffuse term, the dot product of the normal and the unit
+    /// direction to the light clamped at zero, so the side facing a light is brightest at the rim.
+    /// On the lit side it also adds a highlight in the light's own color where the normal is
+    /// halfway between the light and the viewer looking straight down, tightened by the
+    /// material's shininess. The emissive color goes on top, and the far side falls back to the
+    /// ambient level filtered by the albedo.
+    fn shade_sphere(&self, cx: f32, cy: f32, r: f32, material: &Material, x: f32, y: f32) -> Rgb {
+        let [nx, ny, nz] = sphere_normal(cx, cy, r, x, y);
+        let albedo = self.linear(material.albedo);
+        let mut rgb = self.linear(material.emissive);
+        for (i, light) in self.lights.iter().enumerate() {
+            let (lx, ly) = (light.x - x, light.y - y);
+            let l_len = (lx * lx + ly * ly).sqrt();
+            if l_len == 0.0 || !light.shines_on(x, y) {
+                continue;
+            }
+            let (lx, ly) = (lx / l_len, ly / l_len);
+            let diffuse = (nx * lx + ny * ly).max(0.0);
+            if diffuse == 0.0 {
+                continue;
+            }
+            // Halfway between the light, (lx, ly, 0), and the viewer, (0, 0, 1)
+            let specular = if material.shininess > 0.0 {
+                ((nx * lx + ny * ly + nz) / 2.0f32.sqrt()).max(0.0).powf(material.shininess)
+            } else {
+                0.0
+            };
+            for ((c, l), a) in rgb.iter_mut().zip(self.light_color(i)).zip(albedo) {
+                *c += l * (a * diffuse + specular);
+            }
+        }
+        let ambient = self.linear(self.ambient);
+        [0, 1, 2].map(|i| rgb[i].max(ambient[i] * albedo[i]))
+    }
+
+    /// Average of an `aa`×`aa` grid of sub-samples centered on the screen pixel at (x, y). With a
+    /// single sample and `edge_aa` on, pixels on a circle's or light disk's edge are blended by
+    /// `shade_rim` instead.
+    fn shade_pixel(&self, x: f32, y: f32) -> Rgb {
+        let n = self.aa.max(1);
+        if n == 1 {
+            let (x, y) = self.to_world(x, y);
+            return match self.edge_aa.then(|| self.rim_at(x, y)).flatten() {
+                Some(rim) => self.shade_rim(rim, x, y),
+                None => self.shade(x, y),
+            };
+        }
+
+        let mut sum = BLACK;
+        for j in 0..n {
+            for i in 0..n {
+                let sx = x + (i as f32 + 0.5) / n as f32 - 0.5;
+                let sy = y + (j as f32 + 0.5) / n as f32 - 0.5;
+                let (sx, sy) = self.to_world(sx, sy);
+                for (s, c) in sum.iter_mut().zip(self.shade(sx, sy)) {
+                    *s += c;
+                }
+            }
+        }
+        sum.map(|s| s / (n * n) as f32)
+    }
+
+    /// Center and radius of the first light disk, or else circle, whose edge passes within half a
+    /// screen pixel of (x, y).
+    fn rim_at(&self, x: f32, y: f32) -> Option<(f32, f32, f32)> {
+        let half = self.view_transform().1 * 0.5;
+        let near = |cx: f32, cy: f32, r: f32| {
+            let d = (x - cx).hypot(y - cy);
+            ((d - r).abs() < half && d > 0.0).then_some((cx, cy, r))
+        };
+        self.lights.iter().find_map(|l| near(l.x, l.y, l.r))
+            .or_else(|| self.circles.iter().find_map(|c| near(c.x, c.y, c.r)))
+    }
+
+    /// Shade (x, y) on the edge of the disk at (cx, cy) with radius r: the colors just inside and
+    /// just outside it along the radius, mixed by a smoothstep over the screen pixel's width of
+    /// how far in it is. One shade either side of the edge costs far less than supersampling.
+    fn shade_rim(&self, (cx, cy, r): (f32, f32, f32), x: f32, y: f32) -> Rgb {
+        let scale = self.view_transform().1;
+        let (dx, dy) = (x - cx, y - cy);
+        let d = dx.hypot(dy);
+        let at = |dist: f32| self.shade(cx + dx / d * dist, cy + dy / d * dist);
+        let inside = at(d.min(r - RIM_INSET * scale));
+        let outside = at(d.max(r + RIM_INSET * scale));
+        let t = ((r - d) / scale + 0.5).clamp(0.0, 1.0);
+        let k = t * t * (3.0 - 2.0 * t);
+        [0, 1, 2].map(|i| inside[i] * k + outside[i] * (1.0 - k))
+    }
+
+    /// Render into an RGBA `frame` of `self.width`×`self.height` pixels, post-processed with
+    /// whichever of bloom, vignette and negative are on.
+    ///
+    /// The frame is cut into `TILE`×`TILE` tiles (smaller at the right and bottom edges) that rayon
+    /// renders in parallel, each in a tight row-major loop. That keeps each thread on a compact
+    /// patch of the scene and cuts the per-pixel dispatch cost of iterating pixels one by one.
+    ///
+    /// With hard shadows, no supersampling or edge antialiasing, few enough shapes to go without
+    /// the grid, only opaque circles, no floor and the shaded view, rows are shaded `LANES` pixels
+    /// at a time by `draw_lanes`, and whatever is left at the end of a row one pixel at a time.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` isn't the size given to the last `resize`, since every row would then start in
+    /// the wrong place.
+    pub fn draw(&self, frame: &mut [u8]) {
+        self.draw_with_progress(frame, || {});
+    }
+
+    /// Number of tiles `draw` cuts the frame into.
+    pub fn tile_count(&self) -> usize {
+        (self.width as usize).div_ceil(TILE) * (self.height as usize).div_ceil(TILE)
+    }
+
+    /// `draw`, calling `tile_done` once for each tile as it's finished, from whichever thread
+    /// rendered it.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` is the wrong size, as for `draw`.
+    pub fn draw_with_progress(&self, frame: &mut [u8], tile_done: impl Fn() + Sync) {
+        self.shade_frame(frame, tile_done);
+        self.post_process(frame);
+    }
+
+    /// `draw_with_progress` for the next frame of an animation: the trail of the frames before
+    /// is blended in, see `blend_trail`, before the post-processing goes over the result.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` is the wrong size, as for `draw`.
+    pub fn draw_next_with_progress(&mut self, frame: &mut [u8], tile_done: impl Fn() + Sync) {
+        self.shade_frame(frame, tile_done);
+        self.blend_trail(frame);
+        self.post_process(frame);
+    }
+
+    /// The shading part of `draw_with_progress`, leaving out `post_process`.
+    fn shade_frame(&self, frame: &mut [u8], tile_done: impl Fn() + Sync) {
+        self.check_frame(frame);
+        let width = self.width as usize;
+        let tiles_x = width.div_ceil(TILE);
+        let batched = self.shadow_samples <= 1
+            && self.aa <= 1
+            && !self.edge_aa
+            && self.grid.is_none()
+            && !self.translucent
+            && self.scene.floor.is_none()
+            && self.view == View::Shaded;
+
+        // Split the frame into the row segments making up each tile
+        let mut tiles: Vec<(usize, usize, Vec<&mut [u8]>)> = Vec::new();
+        for (band, rows) in frame.chunks_mut(width * 4 * TILE).enumerate() {
+            let first = tiles.len();
+            tiles.extend((0..tiles_x).map(|tx| (tx * TILE, band * TILE, Vec::with_capacity(TILE))));
+            for row in rows.chunks_mut(width * 4) {
+                for (tx, segment) in row.chunks_mut(TILE * 4).enumerate() {
+                    tiles[first + tx].2.push(segment);
+                }
+            }
+        }
+
+        tiles.into_par_iter().for_each(|(x0, y0, rows)| {
+            let mut masks = vec![0; self.lights.len()];
+            for (dy, row) in rows.into_iter().enumerate() {
+                let y = y0 + dy;
+                let split = if batched { row.len() / (4 * LANES) * (4 * LANES) } else { 0 };
+                let (lanes, rest) = row.split_at_mut(split);
+                for (g, group) in lanes.chunks_exact_mut(4 * LANES).enumerate() {
+                    self.draw_lanes(x0 + g * LANES, y, group, &mut masks);
+                }
+                for (dx, pixel) in rest.chunks_exact_mut(4).enumerate() {
+                    let x = x0 + split / 4 + dx;
+                    let rgb = self.shade_pixel(x as f32, y as f32);
+                    pixel.copy_from_slice(&self.encode(rgb, x, y));
+                }
+            }
+            tile_done();
+        });
+    }
+
+    /// Effects over the whole finished frame, in order, each on or off by itself: bloom, then the
+    /// vignette darkening the glow along with the rest, which only the shaded view gets, and
+    /// last the negative. They go on once the frame is otherwise done, after any soft shadow
+    /// passes are averaged and the trail is blended in, so neither blooms twice.
+    fn post_process(&self, frame: &mut [u8]) {
+        if self.view == View::Shaded {
+            if self.bloom {
+                self.bloom_settings.apply(frame, self.width);
+            }
+            if self.vignette {
+                self.vignette_settings.apply(frame, self.width);
+            }
+        }
+        if self.invert {
+            frame.par_chunks_exact_mut(4).for_each(|pixel| {
+                for c in &mut pixel[..3] {
+                    *c = 255 - *c;
+                }
+            });
+        }
+    }
+
+    /// Like `draw`, but while nothing changes, each call shades the frame again with another set
+    /// of shadow samples and shows the average of all of them, so soft shadows lose their noise
+    /// and banding over the next few frames. After `MAX_PASSES` the frame is only re-encoded.
+    ///
+    /// Any change to the scene, the view or the render settings through `update`, `step`,
+    /// `resize` or `reset` starts the average over. With hard shadows there is nothing to refine
+    /// and this is just `draw`.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` is the wrong size, as for `draw`.
+    pub fn draw_progressive(&mut self, frame: &mut [u8]) {
+        if self.dirty {
+            self.dirty = false;
+            self.passes = 0;
+        }
+        if self.shadow_samples <= 1 {
+            self.draw_next_with_progress(frame, || {});
+            return;
+        }
+        self.check_frame(frame);
+
+        let width = self.width as usize;
+        let mut accum = std::mem::take(&mut self.accum);
+        accum.resize(width * self.height as usize, BLACK);
+        if self.passes < MAX_PASSES {
+            self.sample_pass = self.passes;
+            let first = self.passes == 0;
+            accum.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+                for (x, sum) in row.iter_mut().enumerate() {
+                    let rgb = self.shade_pixel(x as f32, y as f32);
+                    for (s, c) in sum.iter_mut().zip(rgb) {
+                        *s = if first { c } else { *s + c };
+                    }
+                }
+            });
+            self.sample_pass = 0;
+            self.passes += 1;
+        }
+
+        let n = self.passes as f32;
+        frame.par_chunks_mut(width * 4).zip(accum.par_chunks(width)).enumerate().for_each(|(y, (pixels, sums))| {
+            for (x, (pixel, sum)) in pixels.chunks_exact_mut(4).zip(sums).enumerate() {
+                pixel.copy_from_slice(&self.encode(sum.map(|s| s / n), x, y));
+            }
+        });
+        self.accum = accum;
+        self.blend_trail(frame);
+        self.post_process(frame);
+    }
+
+    /// With trails on, fade the last frame this blended into the new one in `frame` rather than
+    /// letting the new one replace it, so whatever moves leaves a fading streak: each pixel keeps
+    /// `trail_decay` of its difference from the last frame, which left alone fades away toward
+    /// the new picture. Pixels inside a circle are left as drawn, putting the circles on top of
+    /// their own trails. `draw_progressive` and `draw_next_with_progress` do this between
+    /// shading the frame and post-processing it, so the trail keeps the frames without their
+    /// bloom, which would otherwise build up from one frame to the next.
+    ///
+    /// The first frame, and the first after a `reset` or a resize, has nothing to fade from and
+    /// is left alone.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` is the wrong size, as for `draw`.
+    fn blend_trail(&mut self, frame: &mut [u8]) {
+        if !self.trails {
+            self.trail.clear();
+            return;
+        }
+        self.check_frame(frame);
+        if self.trail.len() == frame.len() {
+            let width = self.width as usize;
+            let decay = self.trail_decay;
+            frame.par_chunks_mut(width * 4).zip(self.trail.par_chunks(width * 4)).enumerate().for_each(|(y, (pixels, last))| {
+                for (x, (pixel, last)) in pixels.chunks_exact_mut(4).zip(last.chunks_exact(4)).enumerate() {
+                    let (wx, wy) = self.to_world(x as f32, y as f32);
+                    if self.circles.iter().any(|c| c.contains(wx, wy)) {
+                        continue;
+                    }
+                    for (c, l) in pixel[..3].iter_mut().zip(last) {
+                        *c = (*c as f32 + decay * (*l as f32 - *c as f32)).round() as u8;
+                    }
+                }
+            });
+        }
+        self.trail.clear();
+        self.trail.extend_from_slice(frame);
+    }
+
+    /// Draw the shadow ray from each light to every `RAY_SPACING`th pixel over `frame`, green
+    /// where any of its light reaches the pixel and red where a shape blocks it. Pixels inside a
+    /// shape or a light's disk have no shadow to show and are skipped.
+    pub fn draw_rays(&self, frame: &mut [u8]) {
+        let ((ox, oy), scale) = self.view_transform();
+        let to_screen = |x: f32, y: f32| (((x - ox) / scale).round() as i32, ((y - oy) / scale).round() as i32);
+        let start = RAY_SPACING / 2;
+        let mut rays = Vec::new();
+        for light in &self.lights {
+            let from = to_screen(light.x, light.y);
+            for sy in (start..self.height).step_by(RAY_SPACING as usize) {
+                for sx in (start..self.width).step_by(RAY_SPACING as usize) {
+                    let (x, y) = self.to_world(sx as f32, sy as f32);
+                    if self.lights.iter().any(|l| l.contains(x, y)) || self.occluder_at(x, y).is_some() {
+                        continue;
+                    }
+                    let blocked = self.transmittance(light.x, light.y, x, y) == BLACK;
+                    rays.push((blocked, from, (sx as i32, sy as i32)));
+                }
+            }
+        }
+        // Blocked rays run on past their blocker, so draw them first and keep the lit ones in front
+        rays.sort_by_key(|&(blocked, ..)| !blocked);
+        for (blocked, from, to) in rays {
+            overlay::draw_line(frame, self.width, from, to, if blocked { RAY_BLOCKED } else { RAY_LIT });
+        }
+    }
+
+    /// The mouse's world position, rounded to whole pixels, and the light or circle it's over,
+    /// or `None` if the readout is off or the mouse is outside the window.
+    fn cursor_readout(&self) -> Option<String> {
+        let (x, y) = self.pointer.filter(|_| self.show_cursor)?;
+        let mut text = format!("{x:.0}, {y:.0}");
+        if let Some(i) = self.lights.iter().position(|l| l.contains(x, y)) {
+            text += &format!(" in light {i}");
+        } else if let Some(i) = self.circles.iter().position(|c| c.contains(x, y)) {
+            text += &format!(" in circle {i}");
+        }
+        Some(text)
+    }
+
+    /// Draw the snapping grid faintly over `frame` while snapping is on, unless zoomed out so
+    /// far its lines would crowd together.
+    pub fn draw_snap_grid(&self, frame: &mut [u8]) {
+        let ((ox, oy), scale) = self.view_transform();
+        let spacing = self.snap_size / scale;
+        if !self.snap || spacing < SNAP_GRID_MIN_SPACING {
+            return;
+        }
+        let origin = (-ox / scale, -oy / scale);
+        overlay::draw_grid(frame, self.width, origin, spacing, SNAP_GRID_COLOR, SNAP_GRID_ALPHA);
+    }
+
+    /// Draw the cursor readout into the bottom-left corner of `frame`, if it's on.
+    pub fn draw_cursor_readout(&self, frame: &mut [u8]) {
+        if let Some(text) = self.cursor_readout() {
+            let (_, h) = overlay::text_size(&text, READOUT_SCALE);
+            overlay::draw_text(frame, self.width, READOUT_MARGIN, self.height.saturating_sub(h + READOUT_MARGIN), &text, READOUT_SCALE);
+        }
+    }
+
+    /// Shade the `LANES` screen pixels starting at (x, y) into `pixels`, with point lights and no
+    /// supersampling.
+    ///
+    /// The circles are tested against all lanes at once with `is_shadowed_x8`, leaving a bit mask
+    /// per light in `masks`; the other shapes are still tested pixel by pixel. The result is the
+    /// same as calling `shade` on each pixel.
+    fn draw_lanes(&self, x: usize, y: usize, pixels: &mut [u8], masks: &mut [u32]) {
+        const ALL: u32 = (1 << LANES) - 1;
+        let ((ox, oy), scale) = self.view_transform();
+        let world_x: [f32; LANES] = std::array::from_fn(|i| ox + (x + i) as f32 * scale);
+        let world_y = oy + y as f32 * scale;
+        let px = f32x8::new(world_x);
+        let py = f32x8::splat(world_y);
+        for (mask, light) in masks.iter_mut().zip(&self.lights) {
+            *mask = 0;
+            for circle in &self.circles {
+                *mask |= circle.blocks_rays_x8(light.x, light.y, px, py);
+                if *mask == ALL {
+                    break;
+                }
+            }
+        }
+
+        for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            let (wx, wy) = (world_x[i], world_y);
+            let rgb = self.shade_with(wx, wy, 0, |l, light| {
+                let blocked = masks[l] & (1 << i) != 0
+                    || self.other_occluders().any(|o| o.blocks_ray(light.x, light.y, wx, wy));
+                if blocked { BLACK } else { WHITE }
+            });
+            pixel.copy_from_slice(&self.encode(rgb, x + i, y));
+        }
+    }
+
+    /// Panic unless `frame` holds `self.width`×`self.height` RGBA pixels.
+    fn check_frame(&self, frame: &[u8]) {
+        assert_eq!(
+            frame.len(),
+            self.width as usize * self.height as usize * 4,
+            "frame must hold {}x{} RGBA pixels",
+            self.width,
+            self.height,
+        );
+    }
+
+    /// Encode a shaded color as output bytes for the screen pixel (x, y).
+    #[inline]
+    fn encode(&self, mut rgb: Rgb, x: usize, y: usize) -> [u8; 4] {
+        // Diagnostic views show their values as they are
+        if self.tonemap && self.view == View::Shaded {
+            rgb = rgb.map(color::aces);
+        }
+        if self.gamma_correct && self.view == View::Shaded {
+            rgb = rgb.map(|c| color::srgb_encode(c.clamp(0.0, 1.0)));
+        }
+        if self.dither && self.view == View::Shaded {
+            return color::to_rgba8_dithered(rgb, x, y);
+        }
+        color::to_rgba8(rgb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::occluder::{Polygon, Rect};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn circle_under_gravity_comes_to_rest_on_the_floor() {
+        let scene = Scene {
+            gravity: 980.0,
+            restitution: 0.7,
+            circles: vec![Circle::new(640.0, 100.0, 50.0, 0.0)],
+            ..Scene::default()
+        };
+        let mut world = World::new(scene);
+        let mut peak_after_bounce = f32::MAX;
+        for _ in 0..600 {
+            world.step(DT);
+            if world.circles[0].vy < 0.0 {
+                peak_after_bounce = peak_after_bounce.min(world.circles[0].y);
+            }
+        }
+        let circle = &world.circles[0];
+        assert_eq!(circle.vy, 0.0);
+        assert!((circle.y - 670.0).abs() < 1e-3, "resting at {}", circle.y);
+        // Each bounce loses energy, so it never climbs back to where it was dropped from
+        assert!(peak_after_bounce > 100.0);
+    }
+
+    #[test]
+    fn circles_bounce_inside_the_play_area() {
+        let mut circle = Circle::new(300.0, 250.0, 40.0, 0.0);
+        circle.vx = 200.0;
+        let scene = Scene {
+            gravity: 980.0,
+            restitution: 0.7,
+            bounds: Some(Bounds { left: 100.0, top: 100.0, right: 500.0, bottom: 400.0 }),
+            circles: vec![circle],
+            ..Scene::default()
+        };
+        scene.validate().unwrap();
+        let mut world = World::new(scene);
+        let (mut min_x, mut max_x) = (f32::MAX, f32::MIN);
+        for _ in 0..600 {
+            world.step(DT);
+            min_x = min_x.min(world.circles[0].x);
+            max_x = max_x.max(world.circles[0].x);
+        }
+        // Each step moves at most a few pixels past an edge before turning back
+        assert!(min_x > 140.0 - 4.0 && max_x < 460.0 + 4.0, "{min_x}..{max_x}");
+        assert!(min_x < 150.0 && max_x > 450.0, "reached both sides");
+        assert!((world.circles[0].y - 360.0).abs() < 1e-3, "resting at {}", world.circles[0].y);
+    }
+
+    #[test]
+    fn wrapping_circles_come_back_in_on_the_far_side() {
+        let mut circle = Circle::new(470.0, 250.0, 40.0, -60.0);
+        circle.vx = 60.0;
+        let scene = Scene {
+            bounds: Some(Bounds { left: 100.0, top: 100.0, right: 500.0, bottom: 400.0 }),
+            wrap: true,
+            circles: vec![circle],
+            ..Scene::default()
+        };
+        let mut world = World::new(scene);
+        // 70 pixels on it would be at x = 540, all the way out past the right edge, so it turns
+        // up all the way out past the left one instead
+        world.step(70.0 / 60.0 + 1e-3);
+        assert!((world.circles[0].x - 60.0).abs() < 0.1, "{}", world.circles[0].x);
+        assert!((world.circles[0].y - 180.0).abs() < 0.1, "{}", world.circles[0].y);
+        assert_eq!((world.circles[0].vx, world.circles[0].vy), (60.0, -60.0));
+    }
+
+    #[test]
+    fn head_on_collision_swaps_equal_circles_velocities() {
+        let mut a = Circle::new(500.0, 360.0, 40.0, 0.0);
+        let mut b = Circle::new(585.0, 360.0, 40.0, 0.0);
+        (a.vx, b.vx) = (100.0, -50.0);
+        let scene = Scene { circles: vec![a, b], ..Scene::default() };
+        let mut world = World::new(scene);
+        for _ in 0..10 {
+            world.step(DT);
+        }
+        let (a, b) = (&world.circles[0], &world.circles[1]);
+        assert!((a.vx + 50.0).abs() < 1e-3 && (b.vx - 100.0).abs() < 1e-3, "{} {}", a.vx, b.vx);
+        assert!(b.x - a.x >= 80.0 - 1e-3, "still overlapping");
+    }
+
+    #[test]
+    fn progressive_passes_refine_the_penumbra_until_the_scene_moves() {
+        let scene = Scene { circles: vec![Circle::new(640.0, 400.0, 80.0, 100.0)], ..Scene::default() };
+        let mut world = World::new(scene);
+        world.resize(160, 90);
+        world.shadow_samples = 4;
+        let mut plain = vec![0; 160 * 90 * 4];
+        world.draw(&mut plain);
+
+        // The first pass takes the same samples as `draw`, later ones others
+        let mut frame = plain.clone();
+        world.draw_progressive(&mut frame);
+        assert_eq!(frame, plain);
+        for _ in 0..4 {
+            world.draw_progressive(&mut frame);
+        }
+        assert_eq!(world.passes, 5);
+        assert_ne!(frame, plain);
+        // `draw` itself is left as it was
+        let mut again = vec![0; frame.len()];
+        world.draw(&mut again);
+        assert_eq!(again, plain);
+
+        world.step(DT);
+        world.draw_progressive(&mut frame);
+        assert_eq!(world.passes, 1);
+    }
+
+    #[test]
+    fn odd_frame_sizes_put_every_pixel_in_place() {
+        // Neither a whole number of tiles nor of lanes across, and narrower than 16:9, so the
+        // scene's height fills the frame and its sides are cut off
+        let (width, height) = (333, 200);
+        let mut world = World::new(Scene::default());
+        world.resize(width, height);
+        let mut frame = vec![0; width as usize * height as usize * 4];
+        world.draw(&mut frame);
+
+        let pixel = |x: u32, y: u32| {
+            let i = (y * width + x) as usize * 4;
+            [frame[i], frame[i + 1], frame[i + 2], frame[i + 3]]
+        };
+        for (x, y) in [(0, 0), (7, 3), (8, 100), (331, 150), (332, 199)] {
+            assert_eq!(pixel(x, y), world.encode(world.shade_pixel(x as f32, y as f32), x as usize, y as usize), "pixel ({x}, {y})");
+        }
+        // The light at (200, 360) lands 200 - (1280 - 333·3.6) / 2 units, 3.6 to a pixel, from the
+        // left edge, halfway down
+        let ((ox, oy), scale) = world.view_transform();
+        let light = &world.lights[0];
+        assert_eq!((((light.x - ox) / scale).round(), ((light.y - oy) / scale).round()), (44.0, 100.0));
+        assert_eq!(pixel(44, 100), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn normals_view_points_the_circle_outwards() {
+        let mut world = World::new(Scene { circles: vec![Circle::new(640.0, 360.0, 100.0, 0.0)], ..Scene::default() });
+        world.view = View::Normals;
+        let normal = |x: f32, y: f32| world.shade(x, y).map(|c| c * 2.0 - 1.0);
+        assert_eq!(normal(640.0, 360.0), [0.0, 0.0, 1.0]);
+        let [nx, ny, nz] = normal(730.0, 360.0);
+        assert!(nx > 0.85 && ny == 0.0 && nz > 0.0, "right side ({nx}, {ny}, {nz})");
+        let [nx, ny, _] = normal(640.0, 270.0);
+        assert!(nx == 0.0 && ny < -0.85, "top ({nx}, {ny})");
+        // Off the circle the plane faces straight up
+        assert_eq!(normal(100.0, 100.0), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn cursor_readout_names_what_is_under_the_mouse() {
+        let mut world = World::new(Scene { circles: vec![Circle::new(640.0, 360.0, 100.0, 0.0)], ..Scene::default() });
+        world.pointer = Some((640.4, 359.6));
+        assert_eq!(world.cursor_readout(), None);
+        world.show_cursor = true;
+        assert_eq!(world.cursor_readout().as_deref(), Some("640, 360 in circle 0"));
+        let light = &world.lights[0];
+        world.pointer = Some((light.x, light.y));
+        assert_eq!(world.cursor_readout(), Some(format!("{:.0}, {:.0} in light 0", light.x, light.y)));
+        world.pointer = Some((10.0, 20.0));
+        assert_eq!(world.cursor_readout().as_deref(), Some("10, 20"));
+    }
+
+    #[test]
+    fn snapping_rounds_to_the_grid() {
+        let mut world = World::new(Scene::default());
+        assert_eq!(world.snap_point(31.0, 49.0), (31.0, 49.0));
+        world.snap = true;
+        world.set_snap_size(25.0);
+        assert_eq!(world.snap_point(31.0, 49.0), (25.0, 50.0));
+        assert_eq!(world.snap_point(-13.0, 0.0), (-25.0, 0.0));
+    }
+
+    #[test]
+    fn progress_counts_every_tile() {
+        let mut world = World::new(Scene::default());
+        world.resize(200, 130);
+        assert_eq!(world.tile_count(), 4 * 3);
+        let done = std::sync::atomic::AtomicUsize::new(0);
+        let mut frame = vec![0; 200 * 130 * 4];
+        world.draw_with_progress(&mut frame, || {
+            done.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        assert_eq!(done.into_inner(), world.tile_count());
+    }
+
+    #[test]
+    fn inverting_comes_after_the_other_effects() {
+        let mut world = World::new(Scene::default());
+        world.resize(128, 72);
+        world.set_vignette(true, Vignette::default());
+        let mut plain = vec![0; 128 * 72 * 4];
+        world.draw(&mut plain);
+        world.invert = true;
+        let mut inverted = vec![0; 128 * 72 * 4];
+        world.draw(&mut inverted);
+        for (p, i) in plain.chunks_exact(4).zip(inverted.chunks_exact(4)) {
+            assert_eq!([255 - p[0], 255 - p[1], 255 - p[2], p[3]], i);
+        }
+    }
+
+    #[test]
+    fn cpu_only_effects_lists_what_the_gpu_would_miss() {
+        let mut world = World::new(Scene::default());
+        assert!(world.cpu_only_effects().is_empty());
+        world.set_trails(true, 0.5);
+        world.shadow_samples = 16;
+        assert_eq!(world.cpu_only_effects(), ["trails", "shadow refinement"]);
+    }
+
+    #[test]
+    fn trails_fade_the_last_frame_out_around_the_circles() {
+        let mut world = World::new(Scene::default());
+        world.resize(128, 72);
+        world.set_trails(true, 0.75);
+        let mut frame = vec![0; 128 * 72 * 4];
+        world.blend_trail(&mut frame);
+        assert!(frame.iter().all(|&c| c == 0), "the first frame has nothing to fade from");
+
+        frame.fill(200);
+        world.blend_trail(&mut frame);
+        let at = |frame: &[u8], x: usize, y: usize| frame[(y * 128 + x) * 4];
+        assert_eq!(at(&frame, 0, 0), 50);
+        assert_eq!(at(&frame, 85, 36), 200, "the circle is drawn over its trail");
+
+        world.set_trails(false, 0.75);
+        frame.fill(200);
+        world.blend_trail(&mut frame);
+        assert_eq!(at(&frame, 0, 0), 200);
+    }
+
+    #[test]
+    fn trails_keep_the_frames_from_before_bloom() {
+        let draw = |bloom| {
+            let mut world = World::new(Scene::default());
+            world.resize(128, 72);
+            world.set_trails(true, 0.75);
+            world.set_bloom(bloom, Bloom::default());
+            let mut frame = vec![0; 128 * 72 * 4];
+            world.draw_next_with_progress(&mut frame, || {});
+            (frame, world.trail)
+        };
+        let ((plain, plain_trail), (bloomed, bloomed_trail)) = (draw(false), draw(true));
+        assert_ne!(plain, bloomed);
+        assert_eq!(plain_trail, bloomed_trail);
+    }
+
+    #[test]
+    fn circle_grids_center_on_the_scene() {
+        let mut scene = Scene::default();
+        scene.arrange_circles(4, 2, 100.0);
+        assert_eq!(scene.circles.len(), 8);
+        assert_eq!((scene.circles[0].x, scene.circles[0].y), (490.0, 310.0));
+        assert_eq!((scene.circles[7].x, scene.circles[7].y), (790.0, 410.0));
+        assert!(scene.circles.iter().all(|c| c.r == 40.0 && c.vy == scene.circles[0].vy));
+        scene.validate().unwrap();
+    }
+
+    #[test]
+    fn floors_reflect_and_darken_under_circles() {
+        use crate::scene::Floor;
+
+        // The default circle's bottom is at y = 510, just above the floor
+        let scene = Scene { floor: Some(Floor::new(520.0)), ..Scene::default() };
+        scene.validate().unwrap();
+        let world = World::new(scene);
+        assert_eq!(world.shade(100.0, 520.0), WHITE, "the line is drawn in the fill color");
+        assert_eq!(world.shade(100.0, 400.0), world.trace_ray(100.0, 400.0, 0));
+
+        let luma = |rgb: Rgb| rgb.iter().sum::<f32>();
+        let (under, aside) = ((850.0, 524.0), (1200.0, 524.0));
+        let darkened = |(x, y): (f32, f32)| luma(world.shade(x, y)) / luma(world.trace_ray(x, y, 0));
+        assert!(darkened(under) < 0.6, "{}", darkened(under));
+        assert!(darkened(aside) > 0.99, "{}", darkened(aside));
+
+        // Deeper down, past the contact shadow, the circle's mirror image shows through
+        let below = world.shade(760.0, 600.0);
+        let reflected = world.trace_ray(760.0, 440.0, 0);
+        let ground = world.trace_ray(760.0, 600.0, 0);
+        let k = 0.3 * (1.0 - 80.0 / 300.0);
+        for i in 0..3 {
+            assert!((below[i] - (ground[i] * (1.0 - k) + reflected[i] * k)).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn each_light_only_takes_its_own_color_out_of_its_shadow() {
+        // Lights to the top left and top right, whose shadows cross under the circle. Each
+        // shadow lies along the line from its light through the circle's center
+        let red = Light::new(340.0, 100.0, 25.0, [0xff, 0, 0]);
+        let blue = Light::new(940.0, 100.0, 25.0, [0, 0, 0xff]);
+        let scene = |lights: Vec<Light>| Scene {
+            lights,
+            circles: vec![Circle::new(640.0, 300.0, 80.0, 0.0)],
+            ..Scene::default()
+        };
+        let both = World::new(scene(vec![red.clone(), blue.clone()]));
+        let only = |light: &Light| World::new(scene(vec![light.clone()]));
+        let (red_shadow, blue_shadow, crossing) = ((820.0, 420.0), (460.0, 420.0), (640.0, 400.0));
+
+        assert_eq!(both.trace_ray(red_shadow.0, red_shadow.1, 0), only(&blue).trace_ray(red_shadow.0, red_shadow.1, 0));
+        assert_eq!(both.trace_ray(blue_shadow.0, blue_shadow.1, 0), only(&red).trace_ray(blue_shadow.0, blue_shadow.1, 0));
+        let lit_blue = both.trace_ray(red_shadow.0, red_shadow.1, 0);
+        assert!(lit_blue[2] > 0.3 && lit_blue[0] == both.linear(both.ambient)[0], "{lit_blue:?}");
+        assert_eq!(both.trace_ray(crossing.0, crossing.1, 0), both.linear(both.ambient));
+    }
+
+    #[test]
+    fn a_light_leaves_its_ambient_in_its_shadow() {
+        let world = |ambient| {
+            let light = Light { ambient, ..Light::new(200.0, 360.0, 25.0, [0xff, 0, 0]) };
+            World::new(Scene { lights: vec![light], circles: vec![Circle::new(640.0, 360.0, 80.0, 0.0)], ..Scene::default() })
+        };
+        let (plain, tinted) = (world([0; 3]), world([0, 0xff, 0]));
+        let (lit, shadow) = ((400.0, 360.0), (900.0, 360.0));
+        assert_eq!(tinted.trace_ray(lit.0, lit.1, 0), plain.trace_ray(lit.0, lit.1, 0));
+        let (dark, green) = (plain.trace_ray(shadow.0, shadow.1, 0), tinted.trace_ray(shadow.0, shadow.1, 0));
+        assert_eq!(dark, plain.linear(plain.ambient));
+        assert!(green[1] > dark[1] && green[0] == dark[0] && green[2] == dark[2], "{green:?}");
+    }
+
+    #[test]
+    fn loading_a_scene_keeps_the_render_settings() {
+        let mut world = World::new(Scene { rects: vec![Rect::new(420.0, 540.0, 80.0, 60.0)], ..Scene::default() });
+        world.resize(320, 180);
+        world.edge_aa = true;
+        world.step(0.5);
+        let scene = Scene {
+            ambient: [1, 2, 3],
+            circles: vec![Circle::new(100.0, 100.0, 10.0, 0.0)],
+            polygons: vec![Polygon::new(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)])],
+            ..Scene::default()
+        };
+        world.load_scene(scene);
+        assert_eq!((world.width, world.height), (320, 180));
+        assert!(world.edge_aa);
+        assert_eq!(world.ambient, [1, 2, 3]);
+        assert_eq!((world.circles.len(), world.circles[0].x, world.circles[0].y), (1, 100.0, 100.0));
+        assert_eq!(world.occluders.len(), 1, "just the new triangle, with the rect gone");
+        assert_eq!(world.time, 0.0);
+    }
+
+    #[test]
+    fn removing_a_light_keeps_the_others_in_step() {
+        use crate::timeline::Keyframe;
+
+        let lights = [100.0, 200.0, 300.0].map(|x| Light::new(x, 100.0, 20.0, [0xff; 3])).to_vec();
+        let keyframes = [0.0, 1.0]
+            .map(|time| Keyframe { time, lights: vec![[100.0, 100.0], [200.0, 100.0 + time * 60.0]], circles: Vec::new() })
+            .to_vec();
+        let mut world = World::new(Scene { lights, keyframes, ..Scene::default() });
+        world.start_orbit(2);
+        world.remove_light(0);
+
+        assert_eq!(world.lights.len(), 2);
+        assert_eq!(world.light_levels.len(), 2);
+        assert_eq!(world.orbit.map(|o| o.light), Some(1));
+        world.step(0.5);
+        assert_eq!((world.lights[0].x, world.lights[0].y), (200.0, 130.0), "the keyframes move the same light");
+
+        world.remove_light(1);
+        assert!(world.orbit.is_none());
+        world.add_light(Light::new(50.0, 50.0, 20.0, [0xff; 3]));
+        assert_eq!(world.light_levels.len(), 2);
+        world.reset();
+        assert_eq!(world.lights.len(), 1, "reset drops the added light but doesn't restore removed ones");
+        world.to_scene().validate().unwrap();
+    }
+
+    #[test]
+    fn overlapping_circles_block_each_sample_once() {
+        // The far circle's shadow lies within the near one's, so together they block no more of
+        // the light than the near one alone, where multiplying their shares would darken it
+        let light = Light::new(200.0, 360.0, 40.0, [0xff; 3]);
+        let (near, far) = (Circle::new(600.0, 360.0, 60.0, 0.0), Circle::new(700.0, 360.0, 60.0, 0.0));
+        let lit = |circles: Vec<Circle>| {
+            let scene = Scene { lights: vec![light.clone()], circles, ..Scene::default() };
+            let mut world = World::new(scene);
+            world.shadow_samples = 64;
+            world.visibility(&world.lights[0], 1000.0, 250.0)[0]
+        };
+        let (both, near_only, far_only) = (lit(vec![near.clone(), far.clone()]), lit(vec![near.clone()]), lit(vec![far]));
+        assert!(0.0 < near_only && near_only < far_only && far_only < 1.0, "{near_only} {far_only}");
+        assert_eq!(both, near_only);
+    }
+
+    #[test]
+    fn edge_antialiasing_blends_only_the_rim() {
+        let mut world = World::new(Scene { circles: vec![Circle::new(640.0, 360.0, 100.0, 0.0)], ..Scene::default() });
+        let hard = |world: &World, x: f32| world.shade_pixel(x, 360.0)[1];
+        // The left edge, facing the light, with the lit background beyond it
+        let (inside, outside) = (hard(&world, 541.0), hard(&world, 539.0));
+        world.edge_aa = true;
+        let rim = hard(&world, 540.0);
+        assert!(inside.min(outside) < rim && rim < inside.max(outside), "{inside} {rim} {outside}");
+        assert_eq!(hard(&world, 541.0), inside);
+        assert_eq!(hard(&world, 539.0), outside);
+    }
+
+    #[test]
+    fn occluders_take_the_fill_color() {
+        let scene = Scene { fill: [0xff, 0, 0], rects: vec![Rect::new(420.0, 540.0, 80.0, 60.0)], ..Scene::default() };
+        let world = World::new(scene);
+        assert_eq!(world.shade(460.0, 570.0), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn reset_restores_the_loaded_scene() {
+        let scene = Scene { circles: vec![Circle::new(640.0, 100.0, 50.0, 200.0)], ..Scene::default() };
+        let mut world = World::new(scene);
+        world.lights[0].x += 300.0;
+        world.camera.pan(50.0, 0.0);
+        for _ in 0..30 {
+            world.step(DT);
+        }
+        world.reset();
+        assert_eq!((world.lights[0].x, world.circles[0].y, world.circles[0].vy), (world.scene.lights[0].x, 100.0, 200.0));
+        assert_eq!(world.camera.offset, (0.0, 0.0));
+        assert_eq!(world.time, 0.0);
+    }
+
+    #[test]
+    fn translucent_circles_each_let_part_of_the_light_through() {
+        let glass = |x, r| Circle { opacity: 0.5, ..Circle::new(x, 360.0, r, 0.0) };
+        let scene = Scene {
+            circles: vec![glass(300.0, 50.0), glass(450.0, 30.0), Circle::new(300.0, 600.0, 50.0, 0.0)],
+            ..Scene::default()
+        };
+        let world = World::new(scene);
+        assert_eq!(world.transmittance(100.0, 360.0, 400.0, 360.0), [0.5; 3]);
+        assert_eq!(world.transmittance(100.0, 360.0, 600.0, 360.0), [0.25; 3]);
+        assert_eq!(world.transmittance(100.0, 600.0, 600.0, 600.0), BLACK);
+        assert_eq!(world.transmittance(100.0, 100.0, 600.0, 100.0), WHITE);
+    }
+
+    #[test]
+    fn colored_glass_tints_the_light_through_it() {
+        let glass = |x, albedo| Circle {
+            opacity: 0.5,
+            material: Material { albedo, ..Material::default() },
+            ..Circle::new(x, 360.0, 40.0, 0.0)
+        };
+        let scene = Scene {
+            circles: vec![glass(300.0, [255, 255, 0]), glass(450.0, [0, 255, 255])],
+            ..Scene::default()
+        };
+        let world = World::new(scene);
+        // Yellow glass stops the blue, and cyan glass behind it the red too
+        assert_eq!(world.transmittance(100.0, 360.0, 400.0, 360.0), [0.5, 0.5, 0.0]);
+        assert_eq!(world.transmittance(100.0, 360.0, 600.0, 360.0), [0.0, 0.25, 0.0]);
+    }
+}
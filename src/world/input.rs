@@ -0,0 +1,344 @@
+//! `World::update`, which drives the world from window input.
+
+use log::warn;
+use rand::Rng;
+use winit::keyboard::KeyCode;
+use winit_input_helper::WinitInputHelper;
+
+use super::{View, World};
+use crate::light::Light;
+use crate::occluder::{Circle, Occluder};
+
+/// Circles spawned with a right click.
+const SPAWN_R: f32 = 40.0;
+const SPAWN_MIN_SPEED: f32 = 40.0;
+const SPAWN_MAX_SPEED: f32 = 120.0;
+/// No more circles are spawned beyond this, to keep the frame time sane.
+const MAX_CIRCLES: usize = 64;
+
+/// Lights added with `J` take the next of these colors in turn, and start this big. No more
+/// are added beyond `MAX_LIGHTS`, since every light costs a shadow ray per pixel.
+const LIGHT_PALETTE: [[u8; 3]; 6] = [
+    [0xff, 0x40, 0x40],
+    [0x40, 0x80, 0xff],
+    [0x40, 0xff, 0x60],
+    [0xff, 0xa0, 0x20],
+    [0xc0, 0x50, 0xff],
+    [0x30, 0xe0, 0xe0],
+];
+const NEW_LIGHT_R: f32 = 25.0;
+const MAX_LIGHTS: usize = 16;
+
+/// Range a light's radius can be scrolled over, and how much one wheel notch scales it by.
+const LIGHT_MIN_R: f32 = 5.0;
+const LIGHT_MAX_R: f32 = 200.0;
+const LIGHT_SCROLL_SCALE: f32 = 1.1;
+
+/// Arrow-key panning speed in screen pixels per second, and the zoom factor of one `+`/`-` press.
+const PAN_SPEED: f32 = 600.0;
+const ZOOM_STEP: f32 = 1.25;
+
+/// How far one Alt+arrow press moves a light, in world units, and with Shift held as well.
+const NUDGE_STEP: f32 = 1.0;
+const NUDGE_STEP_FAST: f32 = 10.0;
+
+/// How fast `Q` and `E` turn a spotlight, in degrees per second.
+const CONE_TURN_SPEED: f32 = 90.0;
+
+/// Simulated time advanced by a single step while paused, in seconds.
+const STEP_DT: f32 = 1.0 / 60.0;
+/// Longest time step taken in one update, in seconds.
+const MAX_DT: f32 = 0.1;
+
+/// Quality levels the `K` and `M` keys cycle through, see `SHADOW_SAMPLES` and `AA`.
+const SHADOW_SAMPLE_STEPS: [u32; 4] = [1, 4, 16, 64];
+const AA_STEPS: [u32; 3] = [1, 2, 4];
+/// Simulation speeds `[` and `]` step down and up through, from stopped to eight times real time.
+const TIME_SCALE_STEPS: [f32; 8] = [0.0, 0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+impl World {
+    /// Handle this frame's input and advance the simulation by `dt` seconds of real time, the
+    /// time since the last update. Velocities are in pixels per second, so motion runs at the same
+    /// speed whatever the frame rate, times `time_scale`. While paused only a single step moves
+    /// anything.
+    ///
+    /// Anything that changes the picture marks the accumulated passes of `draw_progressive` as
+    /// stale.
+    pub fn update(&mut self, input: &WinitInputHelper, dt: f32) {
+        // After a stall (a window drag, a breakpoint) carry on rather than teleporting
+        let elapsed = dt.min(MAX_DT);
+
+        self.pointer = self.cursor(input);
+        if input.key_pressed(KeyCode::KeyI) {
+            self.show_cursor = !self.show_cursor;
+        }
+
+        if input.key_pressed(KeyCode::KeyX) {
+            self.snap = !self.snap;
+        }
+
+        // Slow down or speed up the simulation, down to a standstill
+        let faster = input.key_pressed(KeyCode::BracketRight) as usize;
+        let slower = input.key_pressed(KeyCode::BracketLeft) as usize;
+        if faster != slower {
+            let i = TIME_SCALE_STEPS.iter().position(|&s| s == self.time_scale).unwrap_or(4);
+            let i = (i + faster).saturating_sub(slower).min(TIME_SCALE_STEPS.len() - 1);
+            self.time_scale = TIME_SCALE_STEPS[i];
+        }
+
+        if input.key_pressed(KeyCode::Space) {
+            self.paused = !self.paused;
+        }
+
+        if input.key_pressed(KeyCode::Backspace) {
+            self.reset();
+        }
+
+        if input.key_pressed(KeyCode::KeyL) {
+            self.tint_light_disks = !self.tint_light_disks;
+            self.dirty = true;
+        }
+
+        // Cycle through soft shadow quality levels
+        if input.key_pressed(KeyCode::KeyK) {
+            let next = SHADOW_SAMPLE_STEPS.iter().position(|&n| n == self.shadow_samples).map_or(0, |i| i + 1);
+            self.shadow_samples = SHADOW_SAMPLE_STEPS[next % SHADOW_SAMPLE_STEPS.len()];
+            self.dirty = true;
+        }
+
+        // Cycle through supersampling levels
+        if input.key_pressed(KeyCode::KeyM) {
+            let next = AA_STEPS.iter().position(|&n| n == self.aa).map_or(0, |i| i + 1);
+            self.aa = AA_STEPS[next % AA_STEPS.len()];
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyC) {
+            self.gamma_correct = !self.gamma_correct;
+            self.dirty = true;
+        }
+
+        // Send the active light round the middle of the scene, or stop it
+        if input.key_pressed(KeyCode::KeyO) {
+            if self.orbit.is_some() {
+                self.stop_orbit();
+            } else if let Some(i) = self.active_light(self.cursor(input)) {
+                self.start_orbit(i);
+            }
+        }
+
+        if input.key_pressed(KeyCode::KeyT) {
+            self.tonemap = !self.tonemap;
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyA) {
+            self.edge_aa = !self.edge_aa;
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyB) {
+            self.bloom = !self.bloom;
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyW) {
+            self.trails = !self.trails;
+        }
+
+        if input.key_pressed(KeyCode::F3) {
+            self.invert = !self.invert;
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyV) {
+            self.vignette = !self.vignette;
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyD) {
+            self.dither = !self.dither;
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyH) {
+            self.view = if self.view == View::Penumbra { View::Shaded } else { View::Penumbra };
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::KeyN) {
+            self.view = if self.view == View::Normals { View::Shaded } else { View::Normals };
+            self.dirty = true;
+        }
+
+        // Alt+arrows nudge a light one press (or key repeat) at a time, for exact placement;
+        // otherwise the arrow keys pan, at the same on-screen speed whatever the zoom
+        if input.held_alt() {
+            let axis = |neg, pos| input.key_pressed_os(pos) as i32 as f32 - input.key_pressed_os(neg) as i32 as f32;
+            let step = if input.held_shift() { NUDGE_STEP_FAST } else { NUDGE_STEP };
+            let dx = axis(KeyCode::ArrowLeft, KeyCode::ArrowRight) * step;
+            let dy = axis(KeyCode::ArrowUp, KeyCode::ArrowDown) * step;
+            if (dx, dy) != (0.0, 0.0)
+                && let Some(i) = self.active_light(self.cursor(input))
+            {
+                self.lights[i].x += dx;
+                self.lights[i].y += dy;
+                self.dirty = true;
+            }
+        } else {
+            let axis = |neg, pos| input.key_held(pos) as i32 as f32 - input.key_held(neg) as i32 as f32;
+            let pan_x = axis(KeyCode::ArrowLeft, KeyCode::ArrowRight);
+            let pan_y = axis(KeyCode::ArrowUp, KeyCode::ArrowDown);
+            let (_, scale) = self.view_transform();
+            let pan = PAN_SPEED * elapsed * scale;
+            if (pan_x, pan_y) != (0.0, 0.0) {
+                self.camera.pan(pan_x * pan, pan_y * pan);
+                self.dirty = true;
+            }
+        }
+
+        // Q and E turn the active light's beam, if it has one
+        let turn = input.key_held(KeyCode::KeyE) as i32 as f32 - input.key_held(KeyCode::KeyQ) as i32 as f32;
+        if turn != 0.0
+            && let Some(i) = self.active_light(self.cursor(input))
+            && let Some(cone) = &mut self.lights[i].cone
+        {
+            cone.direction = (cone.direction + turn * CONE_TURN_SPEED * elapsed).rem_euclid(360.0);
+            self.dirty = true;
+        }
+
+        if input.key_pressed(KeyCode::Equal) || input.key_pressed(KeyCode::NumpadAdd) {
+            self.camera.zoom_by(ZOOM_STEP);
+            self.dirty = true;
+        }
+        if input.key_pressed(KeyCode::Minus) || input.key_pressed(KeyCode::NumpadSubtract) {
+            self.camera.zoom_by(1.0 / ZOOM_STEP);
+            self.dirty = true;
+        }
+
+        // J adds a light at the mouse, Delete takes out the active one
+        if input.key_pressed(KeyCode::KeyJ)
+            && let Some((mx, my)) = self.cursor(input)
+        {
+            if self.lights.len() >= MAX_LIGHTS {
+                warn!("Light limit of {MAX_LIGHTS} reached, not adding more");
+            } else {
+                let color = LIGHT_PALETTE[self.lights_added % LIGHT_PALETTE.len()];
+                self.lights_added += 1;
+                self.add_light(Light::new(mx, my, NEW_LIGHT_R, color));
+            }
+        }
+        if input.key_pressed(KeyCode::Delete)
+            && let Some(i) = self.active_light(self.cursor(input))
+        {
+            self.remove_light(i);
+        }
+
+        // Check for mouse press inside a light circle, nearest first where they overlap, or else
+        // inside a bouncing circle
+        if input.mouse_pressed(0)
+            && let Some((mx, my)) = self.cursor(input)
+        {
+            self.dragging = self.nearest_light(mx, my, |l| l.contains(mx, my));
+            // Grabbing the orbiting light takes it out of its orbit
+            if self.dragging.is_some() && self.dragging == self.orbit.map(|o| o.light) {
+                self.stop_orbit();
+            }
+            if self.dragging.is_none() {
+                self.dragging_circle = self.circles.iter().position(|c| c.contains(mx, my));
+            }
+        }
+
+        // While dragging, follow the mouse, or the grid point nearest it when snapping, easing
+        // toward it when smoothed. Compounding the per-step share over however many steps this
+        // frame spans keeps the feel the same at any frame rate
+        if input.mouse_held(0)
+            && let Some((mx, my)) = self.cursor(input)
+        {
+            let (mx, my) = self.snap_point(mx, my);
+            let k = if self.drag_smoothing < 1.0 { 1.0 - (1.0 - self.drag_smoothing).powf(elapsed / STEP_DT) } else { 1.0 };
+            let follow = |x: &mut f32, y: &mut f32| {
+                *x = *x * (1.0 - k) + mx * k;
+                *y = *y * (1.0 - k) + my * k;
+            };
+            if let Some(i) = self.dragging {
+                let light = &mut self.lights[i];
+                follow(&mut light.x, &mut light.y);
+            }
+            if let Some(i) = self.dragging_circle {
+                let circle = &mut self.circles[i];
+                follow(&mut circle.x, &mut circle.y);
+            }
+            self.dirty |= self.dragging.is_some() || self.dragging_circle.is_some();
+        }
+
+        // Scrolling resizes the active light. Each notch scales the radius, and touchpads scroll
+        // by fractions of a notch, so it stays smooth
+        let (_, scroll) = input.scroll_diff();
+        if scroll != 0.0
+            && let Some(cursor) = self.cursor(input)
+            && let Some(i) = self.active_light(Some(cursor))
+        {
+            let light = &mut self.lights[i];
+            light.r = (light.r * LIGHT_SCROLL_SCALE.powf(scroll)).clamp(LIGHT_MIN_R, LIGHT_MAX_R);
+            self.dirty = true;
+        }
+
+        // Right click on empty background spawns a new circle
+        if input.mouse_pressed(1)
+            && let Some((mx, my)) = self.cursor(input)
+            && !self.lights.iter().any(|l| l.contains(mx, my))
+            && self.occluder_at(mx, my).is_none()
+        {
+            if self.circles.len() >= MAX_CIRCLES {
+                warn!("Circle limit of {MAX_CIRCLES} reached, not spawning more");
+            } else {
+                let mut rng = rand::rng();
+                let speed = rng.random_range(SPAWN_MIN_SPEED..SPAWN_MAX_SPEED);
+                let vy = if rng.random_bool(0.5) { speed } else { -speed };
+                self.circles.push(Circle::new(mx, my, SPAWN_R, vy));
+                self.dirty = true;
+            }
+        }
+
+        // Stop dragging when released
+        if input.mouse_released(0) {
+            self.dragging = None;
+            self.dragging_circle = None;
+        }
+
+        let dt = if !self.paused {
+            elapsed * self.time_scale
+        } else if input.key_pressed(KeyCode::Period) {
+            STEP_DT
+        } else {
+            0.0
+        };
+        self.step(dt);
+    }
+
+    /// Simulated seconds per second of real time, as set with `[` and `]`.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// The light that scrolling and nudging act on: the one being dragged, or else the one nearest
+    /// `cursor`, or else the first.
+    fn active_light(&self, cursor: Option<(f32, f32)>) -> Option<usize> {
+        let nearest = cursor.and_then(|(mx, my)| self.nearest_light(mx, my, |_| true));
+        self.dragging.or(nearest).or((!self.lights.is_empty()).then_some(0))
+    }
+
+    /// Of the lights that pass `filter`, the one whose center is nearest (x, y).
+    fn nearest_light(&self, x: f32, y: f32, filter: impl Fn(&Light) -> bool) -> Option<usize> {
+        let dist2 = |l: &Light| (l.x - x).powi(2) + (l.y - y).powi(2);
+        (0..self.lights.len())
+            .filter(|&i| filter(&self.lights[i]))
+            .min_by(|&a, &b| dist2(&self.lights[a]).total_cmp(&dist2(&self.lights[b])))
+    }
+
+    /// World position of the mouse, if it's over the window.
+    fn cursor(&self, input: &WinitInputHelper) -> Option<(f32, f32)> {
+        input.cursor().map(|(sx, sy)| self.to_world(sx, sy))
+    }
+}
@@ -0,0 +1,60 @@
+//! Renders fixed scenes and compares the frames against known hashes, to catch any change to
+//! the CPU renderer's output.
+
+use std::path::Path;
+
+use raytracing_rs::scene::Scene;
+use raytracing_rs::World;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 180;
+const DT: f32 = 1.0 / 60.0;
+
+/// Scene file, physics steps taken before rendering, and the expected FNV-1a hash of the frame.
+const GOLDEN: &[(&str, u32, u64)] = &[
+    ("scenes/default.json", 0, 0x6dc9_3b16_64ca_b4c2),
+    ("scenes/shapes.json", 0, 0x31a2_96ee_3c0c_dbf4),
+    ("scenes/mirror.json", 30, 0x91ee_6487_ef18_ed22),
+    ("scenes/candle.json", 30, 0xb659_9a78_63b1_1ca0),
+    ("scenes/glass.json", 0, 0x53c6_a97f_11f9_db38),
+    ("scenes/spotlight.json", 0, 0x10c0_17c4_d046_c6e7),
+];
+
+/// FNV-1a, which unlike `DefaultHasher` is guaranteed to stay the same across Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn render(path: &str, steps: u32) -> Vec<u8> {
+    let scene = Scene::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).expect("golden scene should load");
+    let mut world = World::new(scene);
+    world.resize(WIDTH, HEIGHT);
+    for _ in 0..steps {
+        world.step(DT);
+    }
+    let mut frame = vec![0; WIDTH as usize * HEIGHT as usize * 4];
+    world.draw(&mut frame);
+    frame
+}
+
+#[test]
+fn frames_match_golden_hashes() {
+    let mut failures = Vec::new();
+    for &(path, steps, expected) in GOLDEN {
+        let hash = fnv1a(&render(path, steps));
+        if hash != expected {
+            failures.push(format!("{path} after {steps} steps: expected {expected:#018x}, got {hash:#018x}"));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "rendered frames changed:\n  {}\nIf the change is intended, check the frames with `--headless` and \
+         copy the new hashes into GOLDEN in tests/golden.rs.",
+        failures.join("\n  "),
+    );
+}
+
+#[test]
+fn rendering_is_repeatable() {
+    assert_eq!(render("scenes/mirror.json", 5), render("scenes/mirror.json", 5));
+}